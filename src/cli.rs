@@ -0,0 +1,478 @@
+use core::fmt;
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+use std::str::FromStr;
+
+use getopts::{Matches, Options};
+
+use crate::compare::{find_ships, Comparison};
+use crate::fleet::{allocate_fleets, AllocationMode, FleetConstraints};
+use crate::gear::{read_gear_from_file, write_gear_to_file, DEFAULT_GEAR_FILE};
+use crate::images::{cache_path, download_image, ImageError, DEFAULT_IMAGE_DIR};
+use crate::query::Expr;
+use crate::roster::apply_roster;
+use crate::score::{sort_ships_weighted, Normalization, Normalizer, Scorer, Weights};
+use crate::ship::*;
+use crate::scrape::{scrape_equipment, scrape_wiki, scrape_wiki_all_levels, Source};
+use crate::level::{map_at_level, MAX_LEVEL, MIN_LEVEL};
+use crate::{
+    apply_account_files, export_json, find_line, group_ships, labelled, read_ships_from_file, sort_ships,
+};
+
+pub const SUBCOMMANDS: [&str; 7] = ["scrape", "sort", "filter", "fleet", "export", "images", "compare"];
+
+// Exit codes
+const EXIT_OK: i32 = 0;
+const EXIT_FAILURE: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+
+#[derive(Debug)]
+pub enum CliError {
+    Usage(String),    // bad arguments, exits with EXIT_USAGE
+    Failed(Box<dyn Error>), // the command itself went wrong
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CliError::Usage(msg) => write!(f, "{}", msg),
+            CliError::Failed(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl<E: Into<Box<dyn Error>>> From<E> for CliError {
+    fn from(e: E) -> Self {
+        CliError::Failed(e.into())
+    }
+}
+
+fn usage_error<T>(msg: impl Into<String>) -> Result<T, CliError> {
+    Err(CliError::Usage(msg.into()))
+}
+
+pub fn is_subcommand(arg: &str) -> bool {
+    SUBCOMMANDS.contains(&arg)
+}
+
+// Run `program <subcommand> [options]` and return the process exit code
+pub fn run(program: &str, subcommand: &str, args: &[String]) -> i32 {
+    let opts = options(subcommand);
+
+    if args.iter().any(|a| a == "-h" || a == "--help") {
+        print_usage(program, subcommand, &opts);
+        return EXIT_OK;
+    }
+
+    let result = match opts.parse(args) {
+        // Only compare takes positional arguments, the ship names
+        Ok(matches) if subcommand != "compare" && !matches.free.is_empty() => {
+            usage_error(format!("unexpected argument \"{}\"", matches.free[0]))
+        }
+        Ok(matches) => match subcommand {
+            "scrape" => scrape(&matches),
+            "sort" => sort(&matches),
+            "filter" => filter(&matches),
+            "fleet" => fleet(&matches),
+            "export" => export(&matches),
+            "images" => images(&matches),
+            "compare" => compare(&matches),
+            _ => usage_error(format!("unknown subcommand \"{}\"", subcommand)),
+        },
+        Err(e) => usage_error(e.to_string()),
+    };
+
+    match result {
+        Ok(()) => EXIT_OK,
+        Err(CliError::Usage(msg)) => {
+            eprintln!("{}: {}", program, msg);
+            print_usage(program, subcommand, &opts);
+            EXIT_USAGE
+        }
+        Err(CliError::Failed(e)) => {
+            eprintln!("{}: error: {}", program, e);
+            EXIT_FAILURE
+        }
+    }
+}
+
+fn options(subcommand: &str) -> Options {
+    let mut opts = Options::new();
+    opts.optflag("h", "help", "show this help");
+
+    if subcommand == "scrape" {
+        opts.optopt("l", "level", "wiki stat table to scrape: 1, 100, 120 or 125", "LEVEL");
+        opts.optflag("a", "all-levels", "scrape every level table; --level picks the default stats");
+        opts.optopt("o", "out", "file to write (default data_export.json)", "FILE");
+        opts.optopt("", "from", "scrape a saved page or directory of pages instead of the live wiki", "PATH");
+        opts.optopt("e", "equipment", "also scrape the equipment lists into FILE, e.g. equipment.json", "FILE");
+        return opts;
+    }
+
+    opts.optopt("i", "input", "ships file to read (default data_export.json)", "FILE");
+    opts.optopt("r", "roster", "only use ships listed in this roster (.txt or .json)", "FILE");
+    opts.optopt("g", "gear", "equipment data for roster loadouts (default equipment.json)", "FILE");
+    opts.optopt("", "skills", "ship skills file (default skills.json if present)", "FILE");
+    opts.optopt("", "tech", "fleet tech bonuses per class (default fleet_tech.json if present)", "FILE");
+    opts.optopt("", "where", "only ships matching a query, e.g. 'class in (CV, CVL) and aviation > 350'", "QUERY");
+    opts.optopt("l", "level", "use stats estimated at any level from 1 to 125 (needs multi-level data)", "LEVEL");
+
+    match subcommand {
+        "sort" => {
+            opts.optopt("L", "line", "backline, frontline, subline or all (default all)", "LINE");
+            opts.optopt("b", "by", "sort keys, e.g. firepower:desc,cost:asc", "KEYS");
+            opts.optopt("w", "weights", "sort by weighted score, e.g. torpedo=1.0,reload=0.6", "WEIGHTS");
+            opts.optopt("n", "normalize", "None, MinMax, ZScore or ClassPercentile", "MODE");
+            opts.optopt("t", "top", "number of ships to print (default 3)", "N");
+            opts.optopt("c", "class", "only ships of this class", "CLASS");
+            opts.optflag("", "show-skills", "list each ship's skills under it");
+        }
+        "filter" => {
+            opts.optopt("L", "line", "backline, frontline, subline or all (default all)", "LINE");
+            opts.optopt("c", "class", "only ships of this class", "CLASS");
+            opts.optopt("a", "armor", "only ships with this armor", "ARMOR");
+            opts.optopt("", "nation", "only ships of this nation, e.g. \"Sakura Empire\"", "NATION");
+            opts.optopt("", "rarity", "only ships of this rarity, e.g. \"Super Rare\"", "RARITY");
+            opts.optopt("", "group-by", "list the ships in groups: nation, rarity or class", "FIELD");
+        }
+        "fleet" => {
+            opts.optopt("w", "weights", "objective weights, e.g. firepower=1.0,aviation=1.0", "WEIGHTS");
+            opts.optopt("n", "normalize", "None, MinMax, ZScore or ClassPercentile", "MODE");
+            opts.optopt("", "max-cost", "maximum total oil cost per fleet", "N");
+            opts.optopt("", "min-classes", "minimum class counts, e.g. CV,DD=2", "CLASSES");
+            opts.optmulti("", "require", "ship that must be in a fleet (repeatable)", "NAME");
+            opts.optopt("s", "subs", "maximum number of submarines (default 3)", "N");
+            opts.optopt("f", "fleets", "number of fleets to form (default 1)", "N");
+            opts.optopt("", "optimize", "Sum or Min of fleet scores (default Sum)", "MODE");
+        }
+        "export" => {
+            opts.optopt("o", "out", "file to write", "FILE");
+        }
+        "images" => {
+            opts.optopt("d", "dir", "image cache directory (default images)", "DIR");
+            opts.optflag("", "refresh", "download again even if already cached");
+        }
+        "compare" => {
+            opts.optflag("", "all-levels", "also compare at each wiki level the data has (needs multi-level data)");
+        }
+        _ => {}
+    }
+    opts
+}
+
+fn print_usage(program: &str, subcommand: &str, opts: &Options) {
+    let brief = match subcommand {
+        "compare" => format!("Usage: {} compare NAME NAME... [options]", program),
+        _ => format!("Usage: {} {} [options]", program, subcommand),
+    };
+    print!("{}", opts.usage(&brief));
+}
+
+// Parse an optional argument, turning a bad value into a usage error
+fn parse_opt<T: FromStr>(matches: &Matches, name: &str) -> Result<Option<T>, CliError> {
+    match matches.opt_str(name) {
+        Some(value) => match value.parse() {
+            Ok(v) => Ok(Some(v)),
+            Err(_) => usage_error(format!("invalid value \"{}\" for --{}", value, name)),
+        },
+        None => Ok(None),
+    }
+}
+
+fn valid_level(matches: &Matches) -> Result<Option<ValidLevel>, CliError> {
+    match parse_opt::<i32>(matches, "level")? {
+        Some(level) => match ValidLevel::from_number(level) {
+            Some(level) => Ok(Some(level)),
+            None => usage_error(format!("level must be 1, 100, 120 or 125, not {}", level)),
+        },
+        None => Ok(None),
+    }
+}
+
+fn load_ships(matches: &Matches) -> Result<HashMap<i32, Ship>, CliError> {
+    let input = matches.opt_str("input").unwrap_or_else(|| String::from("data_export.json"));
+    let mut map = read_ships_from_file(&input).map_err(|e| format!("could not read {}: {}", input, e))?;
+    apply_account_files(&mut map, matches.opt_str("skills").as_deref(), matches.opt_str("tech").as_deref())?;
+    if let Some(level) = parse_opt::<i32>(matches, "level")? {
+        if !(MIN_LEVEL..=MAX_LEVEL).contains(&level) {
+            return usage_error(format!("level must be between {} and {}", MIN_LEVEL, MAX_LEVEL));
+        }
+        let (at_level, skipped) = map_at_level(map, level);
+        if !at_level.is_empty() && skipped.len() == at_level.len() {
+            return usage_error(format!(
+                "--level needs multi-level data, but no ship in {} has it; scrape with --all-levels",
+                input
+            ));
+        }
+        if !skipped.is_empty() {
+            eprintln!("No level tables, keeping their own stats: {}", skipped.join(", "));
+        }
+        map = at_level;
+    }
+
+    if let Some(roster) = matches.opt_str("roster") {
+        // A missing default equipment file only matters if the roster has loadouts
+        let gear = match matches.opt_str("gear") {
+            Some(path) => read_gear_from_file(&path).map_err(|e| format!("could not read {}: {}", path, e))?,
+            None => read_gear_from_file(DEFAULT_GEAR_FILE).unwrap_or_default(),
+        };
+        map = apply_roster(&roster, map, &gear).map_err(|e| format!("could not read {}: {}", roster, e))?;
+    }
+
+    if let Some(query) = matches.opt_str("where") {
+        let query: Expr = query.parse().map_err(|e| CliError::Usage(format!("--where {}", e)))?;
+        map.retain(|_, ship| query.matches(ship));
+    }
+    Ok(map)
+}
+
+fn select_line(matches: &Matches, map: &HashMap<i32, Ship>) -> Result<Vec<Ship>, CliError> {
+    let (mut backline, mut frontline, mut subline) = find_line(map);
+    match matches.opt_str("line").as_deref() {
+        Some("backline") => Ok(backline),
+        Some("frontline") => Ok(frontline),
+        Some("subline") => Ok(subline),
+        Some("all") | None => {
+            backline.append(&mut frontline);
+            backline.append(&mut subline);
+            Ok(backline)
+        }
+        Some(other) => usage_error(format!("unknown line \"{}\"", other)),
+    }
+}
+
+fn scorer(matches: &Matches, map: &HashMap<i32, Ship>, weights: &str) -> Result<Scorer, CliError> {
+    let weights: Weights = weights.parse().map_err(|e: crate::score::WeightsParseError| CliError::Usage(e.to_string()))?;
+    let mode: Normalization = parse_opt(matches, "normalize")?.unwrap_or_default();
+    Ok(Scorer::new(weights, Normalizer::new(map.values(), mode)))
+}
+
+fn scrape(matches: &Matches) -> Result<(), CliError> {
+    let level = valid_level(matches)?.unwrap_or(ValidLevel::Level100);
+    let out = matches.opt_str("out").unwrap_or_else(|| String::from("data_export.json"));
+    let source = matches.opt_str("from").map_or(Source::Live, Source::from_path);
+
+    let (map, report) = if matches.opt_present("all-levels") {
+        scrape_wiki_all_levels(&source, level)?
+    } else {
+        scrape_wiki(&source, level.number())?
+    };
+    eprintln!("{}", report);
+    let (mut all_lines, mut frontline, mut subline) = find_line(&map);
+    all_lines.append(&mut frontline);
+    all_lines.append(&mut subline);
+    export_json(&out, &mut all_lines)?;
+    eprintln!("Wrote {} ships to {}", all_lines.len(), out);
+
+    if let Some(gear_out) = matches.opt_str("equipment") {
+        let (gear, report) = scrape_equipment(&source)?;
+        eprintln!("{}", report);
+        write_gear_to_file(&gear_out, &gear)?;
+        eprintln!("Wrote {} pieces of equipment to {}", gear.len(), gear_out);
+    }
+    Ok(())
+}
+
+fn print_skills(ship: &Ship) {
+    for skill in &ship.skills {
+        println!("     {}", skill);
+    }
+}
+
+fn sort(matches: &Matches) -> Result<(), CliError> {
+    let map = load_ships(matches)?;
+    let mut line = select_line(matches, &map)?;
+    if let Some(class) = parse_opt::<Class>(matches, "class")? {
+        line.retain(|s| s.class.family() == class.family());
+    }
+    let top: usize = parse_opt(matches, "top")?.unwrap_or(3);
+
+    if let Some(weights) = matches.opt_str("weights") {
+        let scorer = scorer(matches, &map, &weights)?;
+        sort_ships_weighted(&mut line, &scorer);
+        for (rank, ship) in line.iter().take(top).enumerate() {
+            println!("{}. {} ({}) {:.2}", rank + 1, ship, ship.class, scorer.score(ship));
+            if matches.opt_present("show-skills") {
+                print_skills(ship);
+            }
+        }
+        return Ok(());
+    }
+
+    let keys = match matches.opt_str("by") {
+        Some(by) => SortKey::parse_chain(&by).map_err(|e| CliError::Usage(e.to_string()))?,
+        None => vec![SortKey::from(SortChoice::HP)],
+    };
+    if keys.is_empty() {
+        return usage_error("--by needs at least one stat");
+    }
+    sort_ships(&mut line, &keys);
+
+    for (rank, ship) in line.iter().take(top).enumerate() {
+        let values = keys
+            .iter()
+            .map(|k| format!("{} {}", k.stat, k.stat.value(ship)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        println!("{}. {} ({}) {}", rank + 1, ship, ship.class, values);
+        if matches.opt_present("show-skills") {
+            print_skills(ship);
+        }
+    }
+    Ok(())
+}
+
+fn filter(matches: &Matches) -> Result<(), CliError> {
+    let class = parse_opt::<Class>(matches, "class")?;
+    let armor = parse_opt::<Armor>(matches, "armor")?;
+    let nation = parse_opt::<Nation>(matches, "nation")?;
+    let rarity = parse_opt::<Rarity>(matches, "rarity")?;
+
+    let map = load_ships(matches)?;
+    let line = select_line(matches, &map)?;
+
+    // Every filter given has to match
+    let mut ships: Vec<&Ship> = line
+        .iter()
+        .filter(|s| {
+            class.as_ref().is_none_or(|class| s.class.family() == class.family())
+                && armor.as_ref().is_none_or(|armor| s.armor == *armor)
+                && nation.as_ref().is_none_or(|nation| s.nation == *nation)
+                && rarity.as_ref().is_none_or(|rarity| s.rarity == *rarity)
+        })
+        .collect();
+    ships.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let print = |ship: &Ship| println!("{} ({}, {}, {}, {})", ship, ship.class, ship.armor, ship.nation, ship.rarity);
+    match matches.opt_str("group-by").as_deref() {
+        None => ships.into_iter().for_each(print),
+        Some(field) => {
+            let groups = match field {
+                "nation" => labelled(group_ships(ships, |s| s.nation.clone())),
+                "rarity" => labelled(group_ships(ships, |s| s.rarity.clone())),
+                "class" => labelled(group_ships(ships, |s| s.class.clone())),
+                other => return usage_error(format!("can't group by \"{}\"", other)),
+            };
+            for (group, members) in groups {
+                println!("{} ({})", group, members.len());
+                members.into_iter().for_each(|ship| {
+                    print!("  ");
+                    print(ship);
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+fn fleet(matches: &Matches) -> Result<(), CliError> {
+    let map = load_ships(matches)?;
+    let weights = matches
+        .opt_str("weights")
+        .unwrap_or_else(|| String::from("firepower=1.0, aviation=1.0, torpedo=0.8"));
+    let scorer = scorer(matches, &map, &weights)?;
+
+    let min_classes = match matches.opt_str("min-classes") {
+        Some(classes) => FleetConstraints::parse_min_classes(&classes).map_err(|e| CliError::Usage(e.to_string()))?,
+        None => Vec::new(),
+    };
+    let constraints = FleetConstraints {
+        min_classes,
+        max_cost: parse_opt(matches, "max-cost")?,
+        required: matches.opt_strs("require"),
+        max_subs: parse_opt(matches, "subs")?.unwrap_or(3),
+        ..FleetConstraints::default()
+    };
+    let count: usize = parse_opt(matches, "fleets")?.unwrap_or(1);
+    if count == 0 {
+        return usage_error("--fleets must be at least 1");
+    }
+    let mode: AllocationMode = parse_opt(matches, "optimize")?.unwrap_or_default();
+
+    let fleets = allocate_fleets(&map, count, &scorer, &constraints, mode)?;
+    for (index, fleet) in fleets.iter().enumerate() {
+        if count > 1 {
+            println!("Fleet {}", index + 1);
+        }
+        println!("{}", fleet);
+    }
+    Ok(())
+}
+
+fn export(matches: &Matches) -> Result<(), CliError> {
+    let out = match matches.opt_str("out") {
+        Some(out) => out,
+        None => return usage_error("--out is required"),
+    };
+    let map = load_ships(matches)?;
+    let mut ships: Vec<Ship> = map.into_values().collect();
+    ships.sort_by(|a, b| a.name.cmp(&b.name));
+    export_json(&out, &mut ships)?;
+
+    eprintln!("Wrote {} ships to {}", ships.len(), out);
+    Ok(())
+}
+
+// Fill the image cache so the GUI has every icon without going online
+fn images(matches: &Matches) -> Result<(), CliError> {
+    let dir = matches.opt_str("dir").unwrap_or_else(|| DEFAULT_IMAGE_DIR.to_string());
+    let dir = Path::new(&dir);
+    let map = load_ships(matches)?;
+    let mut ships: Vec<&Ship> = map.values().collect();
+    ships.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let (mut downloaded, mut cached, mut no_image, mut failed) = (0, 0, 0, 0);
+    for ship in ships {
+        if !matches.opt_present("refresh") && cache_path(dir, ship).is_file() {
+            cached += 1;
+            continue;
+        }
+        match download_image(dir, ship) {
+            Ok(_) => downloaded += 1,
+            Err(ImageError::NoImage) => no_image += 1,
+            Err(e) => {
+                eprintln!("{}: {}", ship.name, e);
+                failed += 1;
+            }
+        }
+    }
+
+    eprintln!(
+        "{} downloaded, {} already cached, {} without an image, {} failed ({})",
+        downloaded,
+        cached,
+        no_image,
+        failed,
+        dir.display()
+    );
+    if failed > 0 {
+        return Err(format!("{} images could not be downloaded", failed).into());
+    }
+    Ok(())
+}
+
+// Line ships up stat by stat, e.g. `compare Hood Renown`
+fn compare(matches: &Matches) -> Result<(), CliError> {
+    if matches.free.len() < 2 {
+        return usage_error("give at least two ship names to compare");
+    }
+    let map = load_ships(matches)?;
+    let ships = find_ships(&map, &matches.free)?;
+    let comparison = Comparison::new(ships);
+    print!("{}", comparison);
+
+    if matches.opt_present("all-levels") {
+        let levels = comparison.levels();
+        if levels.is_empty() {
+            return Err("not every ship has multi-level data; scrape with --all-levels".into());
+        }
+        for level in levels {
+            if let Some(at_level) = comparison.at_level(level) {
+                println!("\n{}", level);
+                print!("{}", at_level);
+            }
+        }
+    }
+    Ok(())
+}
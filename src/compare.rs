@@ -0,0 +1,244 @@
+use core::fmt;
+use std::collections::HashMap;
+use std::error::Error;
+
+use crate::level::ship_at_level;
+use crate::roster::normalize_name;
+use crate::ship::{Ship, SortChoice, SortDirection, ValidLevel};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompareError {
+    TooFew(usize),
+    UnknownShip(String),
+}
+
+impl fmt::Display for CompareError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompareError::TooFew(count) => write!(f, "need at least two ships to compare, got {}", count),
+            CompareError::UnknownShip(name) => write!(f, "no ship named \"{}\"", name),
+        }
+    }
+}
+
+impl Error for CompareError {}
+
+// Look up the ships to compare by name, in the order given. Names are
+// matched the same way as roster entries, so "hood" finds "Hood".
+pub fn find_ships(map: &HashMap<i32, Ship>, names: &[String]) -> Result<Vec<Ship>, CompareError> {
+    if names.len() < 2 {
+        return Err(CompareError::TooFew(names.len()));
+    }
+    names
+        .iter()
+        .map(|name| {
+            let wanted = normalize_name(name);
+            map.values()
+                .find(|ship| normalize_name(&ship.name) == wanted)
+                .cloned()
+                .ok_or_else(|| CompareError::UnknownShip(name.clone()))
+        })
+        .collect()
+}
+
+// One stat across the compared ships
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatRow {
+    pub stat: SortChoice,
+    pub values: Vec<i32>,
+    pub winners: Vec<usize>, // every ship sharing the best value, empty when all are equal or for armor
+}
+
+impl StatRow {
+    fn new(stat: SortChoice, ships: &[Ship]) -> Self {
+        let values: Vec<i32> = ships.iter().map(|ship| stat.value(ship)).collect();
+        let best = match stat.default_direction() {
+            SortDirection::Descending => values.iter().max(),
+            SortDirection::Ascending => values.iter().min(),
+        }
+        .copied();
+
+        // Heavier armor isn't simply better, so nobody wins it
+        let winners = if stat == SortChoice::Armor || values.iter().all(|v| Some(*v) == best) {
+            Vec::new()
+        } else {
+            (0..values.len()).filter(|&i| Some(values[i]) == best).collect()
+        };
+        StatRow { stat, values, winners }
+    }
+
+    // Difference from the first ship
+    pub fn delta(&self, index: usize) -> i32 {
+        self.values[index] - self.values[0]
+    }
+
+    pub fn is_winner(&self, index: usize) -> bool {
+        self.winners.contains(&index)
+    }
+}
+
+// Ships lined up field by field, the first ship being the one the others
+// are measured against
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    pub ships: Vec<Ship>,
+    pub rows: Vec<StatRow>,
+}
+
+impl Comparison {
+    pub fn new(ships: Vec<Ship>) -> Self {
+        let rows = SortChoice::all().into_iter().map(|stat| StatRow::new(stat, &ships)).collect();
+        Comparison { ships, rows }
+    }
+
+    // The same ships at one of the wiki's levels, or None unless every ship
+    // has stats for it
+    pub fn at_level(&self, level: ValidLevel) -> Option<Comparison> {
+        if !self.ships.iter().all(|ship| ship.levels.contains_key(&level)) {
+            return None;
+        }
+        let ships = self.ships.iter().map(|ship| ship_at_level(ship, level.number())).collect::<Option<Vec<_>>>()?;
+        Some(Comparison::new(ships))
+    }
+
+    // Levels every compared ship has stats for
+    pub fn levels(&self) -> Vec<ValidLevel> {
+        ValidLevel::ALL
+            .into_iter()
+            .filter(|level| self.ships.iter().all(|ship| ship.levels.contains_key(level)))
+            .collect()
+    }
+
+    // Number of stats each ship wins outright or shares
+    pub fn wins(&self) -> Vec<usize> {
+        (0..self.ships.len())
+            .map(|index| self.rows.iter().filter(|row| row.is_winner(index)).count())
+            .collect()
+    }
+
+    // Text of a cell: armor by name, other stats with their difference from
+    // the first ship and a * on the best value
+    pub fn cell(&self, row: &StatRow, index: usize) -> String {
+        let ship = &self.ships[index];
+        let mut cell = match row.stat {
+            SortChoice::Armor => ship.armor.to_string(),
+            _ if index == 0 || row.delta(index) == 0 => row.values[index].to_string(),
+            _ => format!("{} ({:+})", row.values[index], row.delta(index)),
+        };
+        if row.is_winner(index) {
+            cell.push_str(" *");
+        }
+        cell
+    }
+}
+
+const STAT_WIDTH: usize = 15;
+const CELL_WIDTH: usize = 20;
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let line = |f: &mut fmt::Formatter, label: &str, cells: Vec<String>| -> fmt::Result {
+            let mut text = format!("{:<STAT_WIDTH$}", label);
+            for cell in cells {
+                text.push_str(&format!("{:<CELL_WIDTH$}", cell));
+            }
+            writeln!(f, "{}", text.trim_end())
+        };
+
+        line(f, "", self.ships.iter().map(|s| s.name.clone()).collect())?;
+        line(f, "Class", self.ships.iter().map(|s| s.class.to_string()).collect())?;
+        line(f, "Nation", self.ships.iter().map(|s| s.nation.to_string()).collect())?;
+        line(f, "Rarity", self.ships.iter().map(|s| s.rarity.to_string()).collect())?;
+        for row in &self.rows {
+            line(f, row.stat.name(), (0..self.ships.len()).map(|i| self.cell(row, i)).collect())?;
+        }
+        line(f, "Stats won", self.wins().iter().map(|wins| wins.to_string()).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scrape::tests::fixture_ships;
+
+    fn compare(names: &[&str]) -> Comparison {
+        let names: Vec<String> = names.iter().map(|name| name.to_string()).collect();
+        Comparison::new(find_ships(&fixture_ships(), &names).unwrap())
+    }
+
+    fn row(comparison: &Comparison, stat: SortChoice) -> &StatRow {
+        comparison.rows.iter().find(|row| row.stat == stat).unwrap()
+    }
+
+    #[test]
+    fn finds_ships_by_name() {
+        let map = fixture_ships();
+        let names = |names: &[&str]| names.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+
+        let ships = find_ships(&map, &names(&["hood", "CASSIN"])).unwrap();
+        assert_eq!(ships[0].name, "Hood");
+        assert_eq!(ships[1].name, "Cassin");
+
+        assert_eq!(find_ships(&map, &names(&["Hood", "Nagato"])).unwrap_err(), CompareError::UnknownShip("Nagato".into()));
+        assert_eq!(find_ships(&map, &names(&["Hood"])).unwrap_err(), CompareError::TooFew(1));
+    }
+
+    #[test]
+    fn higher_is_better_and_cost_lower_is_better() {
+        let comparison = compare(&["Hood", "Cassin"]);
+        assert_eq!(row(&comparison, SortChoice::Firepower).winners, vec![0]);
+        assert_eq!(row(&comparison, SortChoice::Torpedo).winners, vec![1]);
+        // Cassin costs 7 to Hood's 15
+        assert_eq!(row(&comparison, SortChoice::Cost).winners, vec![1]);
+        assert_eq!(row(&comparison, SortChoice::Cost).delta(1), -8);
+    }
+
+    #[test]
+    fn ties() {
+        // Both cost 7, so they share the win over Hood
+        let comparison = compare(&["Cassin", "Javelin (Retrofit)", "Hood"]);
+        assert_eq!(row(&comparison, SortChoice::Cost).winners, vec![0, 1]);
+
+        // Nobody wins a stat every ship has the same value for
+        let aviation = row(&comparison, SortChoice::Aviation);
+        assert_eq!(aviation.values, vec![0, 0, 0]);
+        assert!(aviation.winners.is_empty());
+        assert_eq!(comparison.cell(aviation, 1), "0");
+    }
+
+    #[test]
+    fn armor_never_wins() {
+        let comparison = compare(&["Hood", "Cassin"]);
+        let armor = row(&comparison, SortChoice::Armor);
+        assert_ne!(armor.values[0], armor.values[1]);
+        assert!(armor.winners.is_empty());
+        assert_eq!(comparison.cell(armor, 0), "Medium");
+        assert_eq!(comparison.cell(armor, 1), "Light");
+    }
+
+    #[test]
+    fn wins_and_cells() {
+        let comparison = compare(&["Hood", "Cassin"]);
+        assert_eq!(comparison.wins(), vec![4, 7]);
+        assert_eq!(comparison.cell(row(&comparison, SortChoice::HP), 0), "6838 *");
+        assert_eq!(comparison.cell(row(&comparison, SortChoice::HP), 1), "1446 (-5392)");
+    }
+
+    #[test]
+    fn levels_shared_by_every_ship() {
+        let comparison = compare(&["Hood", "Cassin"]);
+        assert_eq!(comparison.levels(), ValidLevel::ALL.to_vec());
+
+        let at_120 = comparison.at_level(ValidLevel::Level120).unwrap();
+        assert_eq!(at_120.ships[0].hp, 7248);
+        assert_eq!(row(&at_120, SortChoice::HP).values, vec![7248, 1533]);
+
+        // A level one ship lacks is not offered and can't be compared at
+        let mut ships = comparison.ships.clone();
+        ships[1].levels.remove(&ValidLevel::Level125);
+        let partial = Comparison::new(ships);
+        assert_eq!(partial.levels(), vec![ValidLevel::Level1, ValidLevel::Level100, ValidLevel::Level120]);
+        assert!(partial.at_level(ValidLevel::Level125).is_none());
+        assert!(partial.at_level(ValidLevel::Level1).is_some());
+    }
+}
@@ -0,0 +1,680 @@
+use core::fmt;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::str::FromStr;
+
+use strum_macros::EnumString;
+
+use crate::find_line;
+use crate::roster::normalize_name;
+use crate::score::Scorer;
+use crate::ship::{Class, Line, Ship};
+
+pub const VANGUARD_SIZE: usize = 3;
+pub const MAIN_SIZE: usize = 3;
+pub const SUB_SIZE: usize = 3;
+
+#[derive(Debug, Clone)]
+pub struct FleetConstraints {
+    pub min_classes: Vec<(Class, usize)>, // e.g. at least one CV
+    pub max_cost: Option<i32>,
+    pub required: Vec<String>, // ship names that must be in the fleet
+    pub max_subs: usize,
+    pub pool_size: usize, // best ships per line considered when searching
+}
+
+impl Default for FleetConstraints {
+    fn default() -> Self {
+        Self {
+            min_classes: Vec::new(),
+            max_cost: None,
+            required: Vec::new(),
+            max_subs: SUB_SIZE,
+            pool_size: 15,
+        }
+    }
+}
+
+impl FleetConstraints {
+    // Parse "CV, DD=2" into minimum class counts
+    pub fn parse_min_classes(input: &str) -> Result<Vec<(Class, usize)>, FleetError> {
+        let mut classes = Vec::new();
+        for part in input.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (class, count) = match part.split_once('=') {
+                Some((class, count)) => (
+                    class.trim(),
+                    count
+                        .trim()
+                        .parse()
+                        .map_err(|_| FleetError::BadConstraint(part.to_string()))?,
+                ),
+                None => (part, 1),
+            };
+            let class = Class::from_str(class).map_err(|_| FleetError::BadConstraint(part.to_string()))?;
+            classes.push((class, count));
+        }
+        Ok(classes)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FleetError {
+    UnknownShip(String),
+    BadConstraint(String),
+    Infeasible,
+}
+
+impl fmt::Display for FleetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FleetError::UnknownShip(name) => write!(f, "required ship \"{}\" is not in the loaded ships", name),
+            FleetError::BadConstraint(input) => write!(f, "invalid fleet constraint \"{}\"", input),
+            FleetError::Infeasible => write!(f, "no fleet satisfies the constraints"),
+        }
+    }
+}
+
+impl Error for FleetError {}
+
+#[derive(Debug, Clone, Default)]
+pub struct Fleet {
+    pub vanguard: Vec<Ship>,
+    pub main: Vec<Ship>,
+    pub subs: Vec<Ship>,
+    pub score: f64,
+}
+
+impl Fleet {
+    pub fn ships(&self) -> impl Iterator<Item = &Ship> {
+        self.vanguard.iter().chain(self.main.iter()).chain(self.subs.iter())
+    }
+
+    pub fn cost(&self) -> i32 {
+        self.ships().map(|s| s.cost).sum()
+    }
+
+    fn rescore(&mut self, scorer: &Scorer) {
+        self.score = self.ships().map(|s| scorer.score(s)).sum();
+    }
+
+    fn line(&self, line: Line) -> &Vec<Ship> {
+        match line {
+            Line::Vanguard => &self.vanguard,
+            Line::Main => &self.main,
+            Line::Subs => &self.subs,
+        }
+    }
+
+    fn line_mut(&mut self, line: Line) -> &mut Vec<Ship> {
+        match line {
+            Line::Vanguard => &mut self.vanguard,
+            Line::Main => &mut self.main,
+            Line::Subs => &mut self.subs,
+        }
+    }
+
+    // Cost and class constraints plus no ship appearing twice with its retrofit
+    fn is_legal(&self, constraints: &FleetConstraints) -> bool {
+        let bases: HashSet<String> = self.ships().map(base_name).collect();
+        bases.len() == self.ships().count()
+            && self.cost() <= constraints.max_cost.unwrap_or(i32::MAX)
+            && constraints
+                .min_classes
+                .iter()
+                .all(|(class, count)| self.ships().filter(|s| s.class == *class).count() >= *count)
+    }
+}
+
+impl fmt::Display for Fleet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names = |line: &[Ship]| line.iter().map(|s| s.name.as_str()).collect::<Vec<_>>().join(", ");
+        writeln!(f, "Vanguard: {}", names(&self.vanguard))?;
+        writeln!(f, "Main: {}", names(&self.main))?;
+        writeln!(f, "Subs: {}", names(&self.subs))?;
+        write!(f, "Cost: {}  Score: {:.2}", self.cost(), self.score)
+    }
+}
+
+// A candidate group of ships for one line of the fleet
+#[derive(Debug, Clone)]
+struct Combo {
+    ships: Vec<usize>,
+    score: f64,
+    cost: i32,
+}
+
+// Ships of one line with their scores, plus every valid combination of them
+struct LineCandidates {
+    ships: Vec<Ship>,
+    combos: Vec<Combo>,
+}
+
+fn base_name(ship: &Ship) -> String {
+    normalize_name(&ship.name).0
+}
+
+fn combinations(n: usize, k: usize) -> Vec<Vec<usize>> {
+    let mut result = Vec::new();
+    let mut current = Vec::with_capacity(k);
+    fn recurse(start: usize, n: usize, k: usize, current: &mut Vec<usize>, result: &mut Vec<Vec<usize>>) {
+        if current.len() == k {
+            result.push(current.clone());
+            return;
+        }
+        for i in start..n {
+            current.push(i);
+            recurse(i + 1, n, k, current, result);
+            current.pop();
+        }
+    }
+    recurse(0, n, k, &mut current, &mut result);
+    result
+}
+
+impl LineCandidates {
+    fn new(
+        mut line: Vec<Ship>,
+        sizes: &[usize],
+        scorer: &Scorer,
+        constraints: &FleetConstraints,
+        required: &HashSet<String>,
+    ) -> Self {
+        line.sort_by(|a, b| scorer.score(b).total_cmp(&scorer.score(a)));
+
+        // Take the best ships, but always keep required ships and the best few
+        // of any class the constraints ask for so they can still be satisfied
+        let mut pool: Vec<Ship> = Vec::new();
+        let mut per_class: HashMap<Class, usize> = HashMap::new();
+        for ship in line {
+            let class_needed = constraints.min_classes.iter().any(|(c, _)| *c == ship.class)
+                && per_class.get(&ship.class).copied().unwrap_or(0) < MAIN_SIZE;
+            if pool.len() < constraints.pool_size || class_needed || required.contains(&ship.name) {
+                *per_class.entry(ship.class.clone()).or_default() += 1;
+                pool.push(ship);
+            }
+        }
+
+        let required_here: Vec<usize> = (0..pool.len()).filter(|i| required.contains(&pool[*i].name)).collect();
+
+        let mut sizes: Vec<usize> = sizes.iter().map(|s| (*s).min(pool.len())).collect();
+        sizes.dedup();
+
+        let mut combos = Vec::new();
+        for size in sizes {
+            for ships in combinations(pool.len(), size) {
+                if !required_here.iter().all(|r| ships.contains(r)) {
+                    continue;
+                }
+                let bases: HashSet<String> = ships.iter().map(|i| base_name(&pool[*i])).collect();
+                if bases.len() != ships.len() {
+                    continue;
+                }
+                combos.push(Combo {
+                    score: ships.iter().map(|i| scorer.score(&pool[*i])).sum(),
+                    cost: ships.iter().map(|i| pool[*i].cost).sum(),
+                    ships,
+                });
+            }
+        }
+        combos.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+        Self { ships: pool, combos }
+    }
+
+    fn best_score(&self) -> f64 {
+        self.combos.first().map_or(0.0, |c| c.score)
+    }
+
+    fn min_cost(&self) -> i32 {
+        self.combos.iter().map(|c| c.cost).min().unwrap_or(0)
+    }
+
+    fn members<'a>(&'a self, combo: &'a Combo) -> impl Iterator<Item = &'a Ship> {
+        combo.ships.iter().map(|i| &self.ships[*i])
+    }
+}
+
+// Pick the vanguard, main fleet and subs that maximize the scorer's total
+// while satisfying the constraints. Each line is searched over its best
+// `pool_size` ships and combinations are explored best-first, so the first
+// legal fleet found for a pair of surface lines is the best one for it.
+pub fn build_fleet(map: &HashMap<i32, Ship>, scorer: &Scorer, constraints: &FleetConstraints) -> Result<Fleet, FleetError> {
+    build_fleet_excluding(map, scorer, constraints, &HashSet::new())
+}
+
+// Same as build_fleet, but never uses a ship whose base name is in `exclude`
+fn build_fleet_excluding(
+    map: &HashMap<i32, Ship>,
+    scorer: &Scorer,
+    constraints: &FleetConstraints,
+    exclude: &HashSet<String>,
+) -> Result<Fleet, FleetError> {
+    let required: HashSet<String> = constraints
+        .required
+        .iter()
+        .map(|name| {
+            let key = normalize_name(name);
+            map.values()
+                .find(|s| normalize_name(&s.name) == key)
+                .map(|s| s.name.clone())
+                .ok_or_else(|| FleetError::UnknownShip(name.clone()))
+        })
+        .collect::<Result<_, _>>()?;
+
+    let available: HashMap<i32, Ship> = map
+        .iter()
+        .filter(|(_, ship)| !exclude.contains(&base_name(ship)))
+        .map(|(id, ship)| (*id, ship.clone()))
+        .collect();
+    let (backline, frontline, subline) = find_line(&available);
+    let sub_sizes: Vec<usize> = (0..=constraints.max_subs.min(SUB_SIZE)).collect();
+
+    let main = LineCandidates::new(backline, &[MAIN_SIZE], scorer, constraints, &required);
+    let vanguard = LineCandidates::new(frontline, &[VANGUARD_SIZE], scorer, constraints, &required);
+    let subs = LineCandidates::new(subline, &sub_sizes, scorer, constraints, &required);
+
+    let max_cost = constraints.max_cost.unwrap_or(i32::MAX);
+    let mut best_score = f64::NEG_INFINITY;
+    let mut best: Option<(&Combo, &Combo, &Combo)> = None;
+
+    for m in &main.combos {
+        if m.score + vanguard.best_score() + subs.best_score() <= best_score {
+            break;
+        }
+        if m.cost + vanguard.min_cost() + subs.min_cost() > max_cost {
+            continue;
+        }
+        for v in &vanguard.combos {
+            if m.score + v.score + subs.best_score() <= best_score {
+                break;
+            }
+            if m.cost + v.cost + subs.min_cost() > max_cost {
+                continue;
+            }
+            for s in &subs.combos {
+                let total = m.score + v.score + s.score;
+                if total <= best_score {
+                    break;
+                }
+                if m.cost + v.cost + s.cost > max_cost {
+                    continue;
+                }
+
+                let ships: Vec<&Ship> = main.members(m).chain(vanguard.members(v)).chain(subs.members(s)).collect();
+                let bases: HashSet<String> = ships.iter().map(|s| base_name(s)).collect();
+                if bases.len() != ships.len() {
+                    continue;
+                }
+                let classes_met = constraints
+                    .min_classes
+                    .iter()
+                    .all(|(class, count)| ships.iter().filter(|s| s.class == *class).count() >= *count);
+                if !classes_met {
+                    continue;
+                }
+
+                best_score = total;
+                best = Some((m, v, s));
+                break;
+            }
+        }
+    }
+
+    let (m, v, s) = best.ok_or(FleetError::Infeasible)?;
+    Ok(Fleet {
+        vanguard: vanguard.members(v).cloned().collect(),
+        main: main.members(m).cloned().collect(),
+        subs: subs.members(s).cloned().collect(),
+        score: best_score,
+    })
+}
+
+// What the multi-fleet allocator maximizes
+#[derive(EnumString, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[strum(ascii_case_insensitive)]
+pub enum AllocationMode {
+    #[default]
+    Sum, // total of all fleet scores
+    Min, // score of the weakest fleet
+}
+
+impl AllocationMode {
+    pub const ALL: [AllocationMode; 2] = [AllocationMode::Sum, AllocationMode::Min];
+
+    fn objective(&self, scores: impl Iterator<Item = f64>) -> f64 {
+        match self {
+            AllocationMode::Sum => scores.sum(),
+            AllocationMode::Min => scores.fold(f64::INFINITY, f64::min),
+        }
+    }
+}
+
+impl fmt::Display for AllocationMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AllocationMode::Sum => write!(f, "Sum"),
+            AllocationMode::Min => write!(f, "Min"),
+        }
+    }
+}
+
+// Form `count` fleets at once without using any ship (or its retrofit) twice.
+// Fleets are first built greedily one after another, then improved by
+// swapping ships of the same line between fleets, or with unused ships,
+// until no single swap raises the chosen objective.
+pub fn allocate_fleets(
+    map: &HashMap<i32, Ship>,
+    count: usize,
+    scorer: &Scorer,
+    constraints: &FleetConstraints,
+    mode: AllocationMode,
+) -> Result<Vec<Fleet>, FleetError> {
+    let mut fleets: Vec<Fleet> = Vec::with_capacity(count);
+    let mut used: HashSet<String> = HashSet::new();
+
+    for index in 0..count {
+        // Required ships only need to appear in one of the fleets
+        let fleet_constraints = if index == 0 {
+            constraints.clone()
+        } else {
+            FleetConstraints {
+                required: Vec::new(),
+                ..constraints.clone()
+            }
+        };
+        let fleet = build_fleet_excluding(map, scorer, &fleet_constraints, &used)?;
+        used.extend(fleet.ships().map(base_name));
+        fleets.push(fleet);
+    }
+
+    let mut unused: Vec<Ship> = map.values().filter(|s| !used.contains(&base_name(s))).cloned().collect();
+    unused.sort_by(|a, b| scorer.score(b).total_cmp(&scorer.score(a)));
+    unused.truncate(constraints.pool_size * Line::ALL.len());
+
+    // Required ships may move between fleets but never back to the unused pile
+    let required: HashSet<(String, bool)> = constraints.required.iter().map(|name| normalize_name(name)).collect();
+
+    let mut best = mode.objective(fleets.iter().map(|f| f.score));
+    loop {
+        let mut improved = false;
+
+        for a in 0..fleets.len() {
+            for line in Line::ALL {
+                for i in 0..fleets[a].line(line).len() {
+                    // Swap with another fleet
+                    for b in (a + 1)..fleets.len() {
+                        for j in 0..fleets[b].line(line).len() {
+                            let (mut fleet_a, mut fleet_b) = (fleets[a].clone(), fleets[b].clone());
+                            std::mem::swap(&mut fleet_a.line_mut(line)[i], &mut fleet_b.line_mut(line)[j]);
+
+                            if try_accept(&mut fleets, vec![(a, fleet_a), (b, fleet_b)], scorer, constraints, mode, &mut best) {
+                                improved = true;
+                            }
+                        }
+                    }
+
+                    // Swap with a ship nobody is using
+                    if required.contains(&normalize_name(&fleets[a].line(line)[i].name)) {
+                        continue;
+                    }
+                    for spare in unused.iter_mut() {
+                        if Line::of(spare) != line {
+                            continue;
+                        }
+                        let conflicts = fleets
+                            .iter()
+                            .enumerate()
+                            .any(|(n, f)| n != a && f.ships().any(|s| base_name(s) == base_name(spare)));
+                        if conflicts {
+                            continue;
+                        }
+                        let mut fleet_a = fleets[a].clone();
+                        let taken = std::mem::replace(&mut fleet_a.line_mut(line)[i], spare.clone());
+
+                        if try_accept(&mut fleets, vec![(a, fleet_a)], scorer, constraints, mode, &mut best) {
+                            *spare = taken;
+                            improved = true;
+                        }
+                    }
+                }
+            }
+        }
+
+        if !improved {
+            break;
+        }
+    }
+
+    Ok(fleets)
+}
+
+// Put the changed fleets in place of the ones at their indexes if they are
+// legal and the objective gets strictly better. Only the changed fleets are
+// rescored; the others keep their scores.
+fn try_accept(
+    fleets: &mut [Fleet],
+    mut changed: Vec<(usize, Fleet)>,
+    scorer: &Scorer,
+    constraints: &FleetConstraints,
+    mode: AllocationMode,
+    best: &mut f64,
+) -> bool {
+    for (_, fleet) in changed.iter_mut() {
+        fleet.rescore(scorer);
+        if !fleet.is_legal(constraints) {
+            return false;
+        }
+    }
+
+    let scores = fleets.iter().enumerate().map(|(index, fleet)| {
+        changed.iter().find(|(n, _)| *n == index).map_or(fleet.score, |(_, f)| f.score)
+    });
+    let objective = mode.objective(scores);
+    if objective <= *best + 1e-9 {
+        return false;
+    }
+    *best = objective;
+    for (index, fleet) in changed {
+        fleets[index] = fleet;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::score::{Normalization, Normalizer};
+    use crate::scrape::tests::test_ship;
+
+    // Scores are plain HP
+    fn scorer() -> Scorer {
+        Scorer::new("hp=1".parse().unwrap(), Normalizer::new([], Normalization::None))
+    }
+
+    fn pool(ships: &[(&str, Class, i32, i32)]) -> HashMap<i32, Ship> {
+        ships
+            .iter()
+            .enumerate()
+            .map(|(id, (name, class, hp, cost))| {
+                let mut ship = test_ship(name, class.clone());
+                ship.hp = *hp;
+                ship.cost = *cost;
+                (id as i32, ship)
+            })
+            .collect()
+    }
+
+    fn ships() -> HashMap<i32, Ship> {
+        pool(&[
+            ("V1", Class::DD, 500, 10),
+            ("V2", Class::CL, 400, 10),
+            ("V3", Class::CA, 300, 10),
+            ("V4", Class::DD, 200, 10),
+            ("V5", Class::DD, 100, 10),
+            ("B1", Class::BB, 900, 30),
+            ("B2", Class::BB, 800, 10),
+            ("B3", Class::BB, 700, 10),
+            ("C1", Class::CV, 600, 10),
+            ("C2", Class::CV, 100, 10),
+            ("S1", Class::SS, 50, 10),
+            ("S2", Class::SS, 40, 10),
+            ("S3", Class::SS, 30, 10),
+            ("S4", Class::SS, 20, 10),
+        ])
+    }
+
+    fn names(line: &[Ship]) -> Vec<&str> {
+        let mut names: Vec<&str> = line.iter().map(|s| s.name.as_str()).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn picks_the_best_fleet() {
+        let fleet = build_fleet(&ships(), &scorer(), &FleetConstraints::default()).unwrap();
+        assert_eq!(names(&fleet.vanguard), ["V1", "V2", "V3"]);
+        assert_eq!(names(&fleet.main), ["B1", "B2", "B3"]);
+        assert_eq!(names(&fleet.subs), ["S1", "S2", "S3"]);
+        assert_eq!(fleet.score, 3720.0);
+        assert_eq!(fleet.cost(), 110);
+    }
+
+    #[test]
+    fn required_classes() {
+        let constraints = FleetConstraints {
+            min_classes: FleetConstraints::parse_min_classes("CV").unwrap(),
+            ..FleetConstraints::default()
+        };
+        let fleet = build_fleet(&ships(), &scorer(), &constraints).unwrap();
+        assert_eq!(names(&fleet.main), ["B1", "B2", "C1"]);
+
+        let constraints = FleetConstraints {
+            min_classes: FleetConstraints::parse_min_classes("CV=2, DD=3").unwrap(),
+            ..FleetConstraints::default()
+        };
+        let fleet = build_fleet(&ships(), &scorer(), &constraints).unwrap();
+        assert_eq!(names(&fleet.main), ["B1", "C1", "C2"]);
+        assert_eq!(names(&fleet.vanguard), ["V1", "V4", "V5"]);
+    }
+
+    #[test]
+    fn required_ships_and_subs() {
+        let constraints = FleetConstraints {
+            required: vec![String::from("v5")],
+            max_subs: 1,
+            ..FleetConstraints::default()
+        };
+        let fleet = build_fleet(&ships(), &scorer(), &constraints).unwrap();
+        assert_eq!(names(&fleet.vanguard), ["V1", "V2", "V5"]);
+        assert_eq!(names(&fleet.subs), ["S1"]);
+    }
+
+    #[test]
+    fn cost_cap() {
+        // B1 alone costs 30, so the cheapest way under 100 drops the weakest sub
+        let constraints = FleetConstraints {
+            max_cost: Some(100),
+            ..FleetConstraints::default()
+        };
+        let fleet = build_fleet(&ships(), &scorer(), &constraints).unwrap();
+        assert_eq!(names(&fleet.main), ["B1", "B2", "B3"]);
+        assert_eq!(names(&fleet.subs), ["S1", "S2"]);
+        assert_eq!(fleet.cost(), 100);
+
+        for cap in [60, 75, 90] {
+            let constraints = FleetConstraints {
+                max_cost: Some(cap),
+                ..FleetConstraints::default()
+            };
+            assert!(build_fleet(&ships(), &scorer(), &constraints).unwrap().cost() <= cap);
+        }
+    }
+
+    #[test]
+    fn impossible_constraints() {
+        let build = |constraints: FleetConstraints| build_fleet(&ships(), &scorer(), &constraints).unwrap_err();
+        assert_eq!(
+            build(FleetConstraints {
+                min_classes: vec![(Class::CV, 3)],
+                ..FleetConstraints::default()
+            }),
+            FleetError::Infeasible
+        );
+        assert_eq!(
+            build(FleetConstraints {
+                max_cost: Some(50),
+                ..FleetConstraints::default()
+            }),
+            FleetError::Infeasible
+        );
+        assert_eq!(
+            build(FleetConstraints {
+                required: vec![String::from("Hood")],
+                ..FleetConstraints::default()
+            }),
+            FleetError::UnknownShip(String::from("Hood"))
+        );
+        assert_eq!(
+            FleetConstraints::parse_min_classes("CV=two").unwrap_err(),
+            FleetError::BadConstraint(String::from("CV=two"))
+        );
+    }
+
+    // Six ships per surface line, V1 and B1 the strongest
+    fn two_fleets() -> HashMap<i32, Ship> {
+        let mut ships = Vec::new();
+        for (n, hp) in [600, 500, 400, 300, 200, 100].into_iter().enumerate() {
+            ships.push((format!("V{}", n + 1), Class::DD, hp));
+            ships.push((format!("B{}", n + 1), Class::BB, hp * 2));
+        }
+        ships.push((String::from("Javelin"), Class::DD, 50));
+        ships.push((String::from("Javelin (Retrofit)"), Class::DD, 1000));
+        let ships: Vec<(&str, Class, i32, i32)> =
+            ships.iter().map(|(name, class, hp)| (name.as_str(), class.clone(), *hp, 10)).collect();
+        pool(&ships)
+    }
+
+    fn allocate(mode: AllocationMode) -> Vec<Fleet> {
+        allocate_fleets(&two_fleets(), 2, &scorer(), &FleetConstraints::default(), mode).unwrap()
+    }
+
+    #[test]
+    fn no_ship_in_two_fleets() {
+        for mode in AllocationMode::ALL {
+            let fleets = allocate(mode);
+            let bases: Vec<String> = fleets.iter().flat_map(|f| f.ships().map(base_name)).collect();
+            let unique: HashSet<&String> = bases.iter().collect();
+            assert_eq!(unique.len(), bases.len(), "{:?}", bases);
+            assert_eq!(bases.len(), 12);
+            // Javelin and her retrofit count as one ship
+            assert_eq!(bases.iter().filter(|b| *b == "javelin").count(), 1);
+        }
+    }
+
+    #[test]
+    fn min_mode_raises_the_weakest_fleet() {
+        let weakest = |fleets: &[Fleet]| fleets.iter().map(|f| f.score).fold(f64::INFINITY, f64::min);
+        let total = |fleets: &[Fleet]| fleets.iter().map(|f| f.score).sum::<f64>();
+
+        let sum = allocate(AllocationMode::Sum);
+        let min = allocate(AllocationMode::Min);
+        assert!(weakest(&min) > weakest(&sum), "{} vs {}", weakest(&min), weakest(&sum));
+        // Swaps between fleets move score around without losing any
+        assert_eq!(total(&min), total(&sum));
+        for fleet in &min {
+            assert_eq!(fleet.score, fleet.ships().map(|s| s.hp as f64).sum::<f64>());
+        }
+    }
+
+    #[test]
+    fn required_ship_stays_allocated() {
+        let constraints = FleetConstraints {
+            required: vec![String::from("V6")],
+            ..FleetConstraints::default()
+        };
+        for mode in AllocationMode::ALL {
+            let fleets = allocate_fleets(&two_fleets(), 2, &scorer(), &constraints, mode).unwrap();
+            assert!(fleets.iter().any(|f| f.ships().any(|s| s.name == "V6")));
+        }
+    }
+}
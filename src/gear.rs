@@ -0,0 +1,193 @@
+use core::fmt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+use strum_macros::EnumString;
+
+use crate::ship::{Class, Nation, Rarity, Ship, StatBlock};
+
+pub const DEFAULT_GEAR_FILE: &str = "equipment.json";
+
+// Every ship has three main slots and two auxiliary slots
+pub const MAIN_SLOTS: usize = 3;
+pub const SLOT_COUNT: usize = 5;
+
+#[derive(EnumString, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+#[strum(ascii_case_insensitive)]
+pub enum GearType {
+    #[strum(serialize = "DDGun", serialize = "DD Gun")]
+    DDGun,
+    #[strum(serialize = "CLGun", serialize = "CL Gun")]
+    CLGun,
+    #[strum(serialize = "CAGun", serialize = "CA Gun")]
+    CAGun,
+    #[strum(serialize = "CBGun", serialize = "CB Gun")]
+    CBGun,
+    #[strum(serialize = "BBGun", serialize = "BB Gun")]
+    BBGun,
+    Torpedo,
+    #[strum(serialize = "SubTorpedo", serialize = "Submarine Torpedo")]
+    SubTorpedo,
+    #[strum(serialize = "AAGun", serialize = "AA Gun")]
+    AAGun,
+    Fighter,
+    #[strum(serialize = "DiveBomber", serialize = "Dive Bomber")]
+    DiveBomber,
+    #[strum(serialize = "TorpedoBomber", serialize = "Torpedo Bomber")]
+    TorpedoBomber,
+    Seaplane,
+    #[strum(serialize = "ASW", serialize = "Anti-Submarine")]
+    ASW,
+    Auxiliary,
+    Cargo,
+}
+
+impl fmt::Display for GearType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GearType::DDGun => write!(f, "DD Gun"),
+            GearType::CLGun => write!(f, "CL Gun"),
+            GearType::CAGun => write!(f, "CA Gun"),
+            GearType::CBGun => write!(f, "CB Gun"),
+            GearType::BBGun => write!(f, "BB Gun"),
+            GearType::SubTorpedo => write!(f, "Submarine Torpedo"),
+            GearType::AAGun => write!(f, "AA Gun"),
+            GearType::DiveBomber => write!(f, "Dive Bomber"),
+            GearType::TorpedoBomber => write!(f, "Torpedo Bomber"),
+            _ => write!(f, "{:?}", self),
+        }
+    }
+}
+
+// A piece of equipment and the stats it adds to the ship carrying it
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Gear {
+    pub name: String,
+    pub gear_type: GearType,
+    pub rarity: Rarity,
+    pub nation: Nation,
+    #[serde(default)]
+    pub stats: StatBlock,
+}
+
+const AUX_SLOT: &[GearType] = &[GearType::Auxiliary, GearType::ASW, GearType::Cargo];
+const PLANES: &[GearType] = &[GearType::Fighter, GearType::DiveBomber, GearType::TorpedoBomber];
+const SECONDARY_GUNS: &[GearType] = &[GearType::DDGun, GearType::CLGun, GearType::CAGun];
+
+// Gear types each main slot of a hull class accepts. This is the usual
+// layout for the class; ships with their own special slots (CLs that take
+// CA guns, CVs that carry seaplanes, ...) are not modelled.
+fn main_slots(class: &Class) -> [&'static [GearType]; MAIN_SLOTS] {
+    match class {
+        Class::DD => [&[GearType::DDGun], &[GearType::Torpedo], &[GearType::AAGun]],
+        Class::CL => [&[GearType::CLGun, GearType::DDGun], &[GearType::Torpedo, GearType::AAGun], &[GearType::AAGun]],
+        Class::CA => [&[GearType::CAGun], &[GearType::Torpedo, GearType::AAGun, GearType::CLGun], &[GearType::AAGun]],
+        Class::CB => [&[GearType::CBGun], SECONDARY_GUNS, &[GearType::AAGun]],
+        Class::BB | Class::BC | Class::BM => [&[GearType::BBGun], SECONDARY_GUNS, &[GearType::AAGun]],
+        Class::BBV => [&[GearType::BBGun], SECONDARY_GUNS, &[GearType::Seaplane, GearType::AAGun]],
+        Class::CV | Class::CVL => [PLANES, PLANES, PLANES],
+        Class::SS => [&[GearType::SubTorpedo], &[GearType::SubTorpedo], &[]],
+        Class::SSV => [&[GearType::SubTorpedo], &[GearType::Seaplane], &[]],
+        _ => [&[GearType::DDGun, GearType::CLGun], &[GearType::Torpedo, GearType::AAGun], &[GearType::AAGun]],
+    }
+}
+
+// Gear types a slot (0 based) of a hull class accepts
+pub fn slot_types(class: &Class, slot: usize) -> &'static [GearType] {
+    match slot {
+        0..MAIN_SLOTS => main_slots(class)[slot],
+        MAIN_SLOTS..SLOT_COUNT => AUX_SLOT,
+        _ => &[],
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GearError {
+    UnknownGear(String),
+    WrongSlot { gear: String, gear_type: GearType, class: Class, slot: usize },
+    TooMany(usize),
+}
+
+impl fmt::Display for GearError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GearError::UnknownGear(name) => write!(f, "no equipment named \"{}\"", name),
+            GearError::WrongSlot { gear, gear_type, class, slot } => {
+                write!(f, "{} can't go in slot {} of a {} ({})", gear, slot + 1, class, gear_type)
+            }
+            GearError::TooMany(count) => write!(f, "{} slots given but ships only have {}", count, SLOT_COUNT),
+        }
+    }
+}
+
+impl Error for GearError {}
+
+pub fn check_slot(class: &Class, slot: usize, gear: &Gear) -> Result<(), GearError> {
+    if slot_types(class, slot).contains(&gear.gear_type) {
+        Ok(())
+    } else {
+        Err(GearError::WrongSlot {
+            gear: gear.name.clone(),
+            gear_type: gear.gear_type,
+            class: class.clone(),
+            slot,
+        })
+    }
+}
+
+fn gear_key(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+// Equipment data keyed by lowercased name
+pub fn read_gear_from_file<P: AsRef<Path>>(path: P) -> Result<HashMap<String, Gear>, Box<dyn Error>> {
+    let file = File::open(path)?;
+    let reader = BufReader::new(file);
+
+    let gear: Vec<Gear> = serde_json::from_reader(reader)?;
+
+    Ok(gear.into_iter().map(|g| (gear_key(&g.name), g)).collect())
+}
+
+pub fn write_gear_to_file<P: AsRef<Path>>(path: P, gear: &[Gear]) -> std::io::Result<()> {
+    let file = File::create(path)?;
+    let mut writer = BufWriter::new(file);
+    serde_json::to_writer(&mut writer, gear)?;
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn find_gear<'a>(gear: &'a HashMap<String, Gear>, name: &str) -> Option<&'a Gear> {
+    gear.get(&gear_key(name))
+}
+
+// Look up a loadout (one name per slot, None for an empty slot) and keep the
+// pieces the ship can actually equip. Everything else comes back as errors.
+pub fn equip(ship: &Ship, loadout: &[Option<String>], gear: &HashMap<String, Gear>) -> (Vec<Gear>, Vec<GearError>) {
+    let mut equipped = Vec::new();
+    let mut errors = Vec::new();
+
+    if loadout.len() > SLOT_COUNT {
+        errors.push(GearError::TooMany(loadout.len()));
+    }
+
+    for (slot, name) in loadout.iter().enumerate().take(SLOT_COUNT) {
+        let Some(name) = name else { continue };
+        match find_gear(gear, name) {
+            Some(piece) => match check_slot(&ship.class, slot, piece) {
+                Ok(()) => equipped.push(piece.clone()),
+                Err(e) => errors.push(e),
+            },
+            None => errors.push(GearError::UnknownGear(name.clone())),
+        }
+    }
+    (equipped, errors)
+}
+
+// Total stats added by a set of equipment
+pub fn gear_bonus(gear: &[Gear]) -> StatBlock {
+    gear.iter().fold(StatBlock::default(), |total, g| total + g.stats)
+}
@@ -1,39 +1,202 @@
 use crate::find_line;
+use crate::compare::Comparison;
+use crate::{filter_line_armor, filter_line_class, group_ships, labelled};
+use crate::sort_ships;
+use crate::fleet::{allocate_fleets, AllocationMode, Fleet, FleetConstraints};
 use crate::read_ships_from_file;
+use crate::apply_account_files;
+use crate::gear::{read_gear_from_file, DEFAULT_GEAR_FILE};
+use crate::images::{download_image, ImageCache, ImageError, DEFAULT_IMAGE_DIR};
+use crate::query::Expr;
+use crate::radar::RadarChart;
+use crate::roster::{apply_roster, default_roster_path};
+use crate::score::{sort_ships_weighted, Normalization, Normalizer, Scorer, Weights};
+use crate::scrape::{parse_all_levels, Source, SHIP_STATS_PAGE};
+use crate::export_json;
 
 use iced::widget::button::Button;
 use iced::widget::column;
 use iced::widget::image;
+use iced::widget::progress_bar;
 use iced::widget::radio;
-use iced::widget::{pick_list, row, Checkbox};
+use iced::widget::{canvas, container, pick_list, row, scrollable, Checkbox};
 use iced::widget::text;
-use iced::{Sandbox, Settings};
+use iced::widget::text_input;
+use iced::futures::channel::{mpsc, oneshot};
+use iced::{executor, Application, Command, Settings};
 use iced::Alignment;
 use iced::Length;
 
 use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
 use crate::ship::*;
 
+const FLEET_COUNTS: [usize; 4] = [1, 2, 3, 4];
+const TOP_COUNTS: [usize; 6] = [1, 3, 5, 6, 10, 20];
+
+// Ships shown in each line on the Lines view
+const PREVIEW_COUNT: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum View {
+    Lines,
+    Table,
+    Compare,
+    Fleets,
+}
+
+// What the table splits its line into, each group under its own heading
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GroupBy {
+    #[default]
+    Nothing,
+    Nation,
+    Rarity,
+    Class,
+}
+
+impl GroupBy {
+    pub const ALL: [GroupBy; 4] = [GroupBy::Nothing, GroupBy::Nation, GroupBy::Rarity, GroupBy::Class];
+}
+
+impl std::fmt::Display for GroupBy {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            GroupBy::Nothing => write!(f, "No groups"),
+            _ => write!(f, "{:?}", self),
+        }
+    }
+}
+
+// Work that runs in the background so the window keeps responding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskKind {
+    Import,
+    Scrape,
+    Images,
+}
+
+impl std::fmt::Display for TaskKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TaskKind::Import => write!(f, "Importing ships"),
+            TaskKind::Scrape => write!(f, "Scraping the wiki"),
+            TaskKind::Images => write!(f, "Downloading images"),
+        }
+    }
+}
+
+// The task in progress. Results carry the id of the task that produced
+// them, so anything arriving after a cancel is ignored.
+struct Task {
+    id: u64,
+    kind: TaskKind,
+    done: usize,
+    total: usize,
+    cancelled: Arc<AtomicBool>,
+}
+
+// Run blocking work (file reads, network requests) on its own thread and
+// wait for it without holding up the GUI executor
+async fn in_background<T: Send + 'static>(
+    work: impl FnOnce() -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+    let (sender, receiver) = oneshot::channel();
+    thread::spawn(move || {
+        let _ = sender.send(work());
+    });
+    receiver.await.unwrap_or_else(|_| Err(String::from("the background task stopped unexpectedly")))
+}
+
+// Read data_export.json with the account files and, unless importing
+// everything, narrow it to the roster
+fn import_ships(import_all: bool) -> Result<HashMap<i32, Ship>, Box<dyn std::error::Error>> {
+    let mut map = read_ships_from_file("data_export.json")?;
+    if let Err(e) = apply_account_files(&mut map, None, None) {
+        eprintln!("{}", e);
+    }
+    if !import_all {
+        map = apply_roster(default_roster_path(), map, &read_gear_from_file(DEFAULT_GEAR_FILE).unwrap_or_default())?;
+    }
+    Ok(map)
+}
+
+// Scrape every level table from the live wiki and save it to
+// data_export.json, the same as the terminal menu's "AllLevels" scrape
+fn scrape_ships(cancelled: &AtomicBool) -> Result<(HashMap<i32, Ship>, String), Box<dyn std::error::Error>> {
+    let html = Source::Live.fetch(SHIP_STATS_PAGE)?;
+    if cancelled.load(Ordering::Relaxed) {
+        return Err("cancelled".into());
+    }
+    let (map, report) = parse_all_levels(&html, ValidLevel::Level120)?;
+    // Don't overwrite data_export.json once the scrape has been cancelled
+    if cancelled.load(Ordering::Relaxed) {
+        return Err("cancelled".into());
+    }
+    eprintln!("{}", report);
+    let mut ships: Vec<Ship> = map.values().cloned().collect();
+    export_json("data_export.json", &mut ships)?;
+    Ok((map, format!("{} rows read, {} skipped", report.rows_read, report.errors.len())))
+}
+
 pub struct GUI {
     map: HashMap<i32, Ship>,
     backline: Vec<Ship>,
     frontline: Vec<Ship>,
     subline: Vec<Ship>,
-    backline_img: Vec<image::Handle>,
-    frontline_img: Vec<image::Handle>,
-    subline_img: Vec<image::Handle>,
-    import_all: bool, // whether to import all or use the include.txt
+    images: ImageCache,
+    import_all: bool, // whether to import all or use the roster (include.json or include.txt)
     frontline_sort: SortChoice,
     frontline_class_filter: Option<Class>,
     backline_sort: SortChoice,
     backline_class_filter: Option<Class>,
     subline_sort: SortChoice,
     subline_class_filter: Option<Class>,
+    frontline_direction: SortDirection,
+    backline_direction: SortDirection,
+    subline_direction: SortDirection,
+    tie_breakers: String, // extra sort keys applied after each line's own stat
+    tie_breakers_error: Option<String>,
+    query_input: String,  // only ships matching this query are sorted into the lines
+    query_error: Option<String>,
+    armor_filter: Option<Armor>,
+    nation_filter: Option<Nation>,
+    rarity_filter: Option<Rarity>,
+    use_weights: bool,
+    weights_input: String,
+    weights_error: Option<String>,
+    normalization: Normalization,
+    scorer: Option<Scorer>, // scorer used for the last sort, if any
+    view: View,
+    fleet_count: usize,
+    allocation_mode: AllocationMode,
+    fleet_max_cost: String,
+    fleet_min_classes: String,
+    fleets: Vec<Fleet>,
+    fleet_error: Option<String>,
+    table_line: Line,
+    table_group: GroupBy,
+    top_count: usize, // rows highlighted at the top of the table
+    selected: Option<String>, // id of the ship shown in the detail panel
+    radar: RadarChart,        // the selected ship against its class, kept until the selection or ships change
+    compare_ids: Vec<String>, // ships on the Compare view, the first one is the baseline
+    compare_level: Option<ValidLevel>, // compare at a wiki level instead of current stats
+    task: Option<Task>,
+    next_task: u64,
+    status: String,
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
     ImportShips,
+    Imported(u64, Result<HashMap<i32, Ship>, String>),
+    ScrapeWiki,
+    Scraped(u64, Result<(HashMap<i32, Ship>, String), String>),
+    ImageLoaded(u64, String, Result<PathBuf, ImageError>),
+    CancelTask,
     SortShips,
     ClearLines,
     FrontlineSort(SortChoice),
@@ -43,12 +206,38 @@ pub enum Message {
     FrontlineClassFilter(Class),
     BacklineClassFilter(Class),
     SublineClassFilter(Class),
+    UseWeightsToggle(bool),
+    WeightsInput(String),
+    NormalizationPick(Normalization),
+    FrontlineDirection(SortDirection),
+    BacklineDirection(SortDirection),
+    SublineDirection(SortDirection),
+    TieBreakersInput(String),
+    TableLine(Line),
+    TableGroup(GroupBy),
+    TableSort(SortChoice),
+    TopCount(usize),
+    SelectShip(String),
+    CloseDetail,
+    AddToCompare(String),
+    RemoveFromCompare(String),
+    ClearCompare,
+    CompareLevel(Option<ValidLevel>),
+    QueryInput(String),
+    ArmorFilter(Armor),
+    NationFilter(Nation),
+    RarityFilter(Rarity),
+    ClearFilters,
+    ShowView(View),
+    FleetCountPick(usize),
+    AllocationModePick(AllocationMode),
+    FleetMaxCostInput(String),
+    FleetMinClassesInput(String),
+    AllocateFleets,
 }
 
 impl GUI {
     pub fn new() -> Self {
-        let image_test: image::Handle = image::Handle::from_path("test.png");
-
         Self {
             map: HashMap::new(),
             backline: Vec::new(),
@@ -61,118 +250,827 @@ impl GUI {
             frontline_class_filter: None,
             backline_class_filter: None,
             subline_class_filter: None,
-            backline_img: vec![image_test.clone(); 3],
-            frontline_img: vec![image_test.clone(); 3],
-            subline_img: vec![image_test.clone(); 3],
+            images: ImageCache::new(DEFAULT_IMAGE_DIR),
+            frontline_direction: SortDirection::Descending,
+            backline_direction: SortDirection::Descending,
+            subline_direction: SortDirection::Descending,
+            tie_breakers: String::from("cost:asc"),
+            tie_breakers_error: None,
+            query_input: String::new(),
+            query_error: None,
+            armor_filter: None,
+            nation_filter: None,
+            rarity_filter: None,
+            use_weights: false,
+            weights_input: String::from("firepower=1.0, reload=0.5"),
+            weights_error: None,
+            normalization: Normalization::None,
+            scorer: None,
+            view: View::Lines,
+            fleet_count: 2,
+            allocation_mode: AllocationMode::Sum,
+            fleet_max_cost: String::new(),
+            fleet_min_classes: String::from("CV"),
+            fleets: Vec::new(),
+            fleet_error: None,
+            table_line: Line::Main,
+            table_group: GroupBy::default(),
+            top_count: 3,
+            selected: None,
+            radar: RadarChart::default(),
+            compare_ids: Vec::new(),
+            compare_level: None,
+            task: None,
+            next_task: 0,
+            status: String::from("Ready"),
         }
-
     }
 
     pub fn start(&self) -> Result<(), iced::Error> {
         GUI::run(Settings::default())
     }
-}
 
-impl Sandbox for GUI {
-    type Message = Message;
+    // Ship name, with its composite score when sorted by weights
+    fn ship_label(&self, ship: &Ship) -> String {
+        match &self.scorer {
+            Some(scorer) => format!("{} ({:.2})", ship, scorer.score(ship)),
+            None => ship.to_string(),
+        }
+    }
 
-    fn theme(&self) -> iced::Theme {
-        iced::Theme::Dark
+    fn line(&self, line: Line) -> &[Ship] {
+        match line {
+            Line::Main => &self.backline,
+            Line::Vanguard => &self.frontline,
+            Line::Subs => &self.subline,
+        }
     }
 
-    fn style(&self) -> iced::theme::Application {
-        iced::theme::Application::default()
+    // The stat and direction a line is sorted by
+    fn line_sort(&self, line: Line) -> (SortChoice, SortDirection) {
+        match line {
+            Line::Main => (self.backline_sort, self.backline_direction),
+            Line::Vanguard => (self.frontline_sort, self.frontline_direction),
+            Line::Subs => (self.subline_sort, self.subline_direction),
+        }
     }
 
-    fn scale_factor(&self) -> f64 {
-        1.0
+    fn line_sort_mut(&mut self, line: Line) -> (&mut SortChoice, &mut SortDirection) {
+        match line {
+            Line::Main => (&mut self.backline_sort, &mut self.backline_direction),
+            Line::Vanguard => (&mut self.frontline_sort, &mut self.frontline_direction),
+            Line::Subs => (&mut self.subline_sort, &mut self.subline_direction),
+        }
     }
 
-    fn run(settings: iced::Settings<()>) -> Result<(), iced::Error>
-    where
-        Self: 'static + Sized,
-    {
-        <Self as iced::Application>::run(settings)
+    // Values of a field across the loaded ships, for the filter pickers. This
+    // includes any Unknown nations or rarities the data has.
+    fn choices<T: Ord + Clone>(&self, field: fn(&Ship) -> &T) -> Vec<T> {
+        let mut values: Vec<T> = self.map.values().map(|s| field(s).clone()).collect();
+        values.sort();
+        values.dedup();
+        values
     }
 
-    fn new() -> Self {
-        let image_test: image::Handle = image::Handle::from_path("test.png");
+    // Split the ships into lines, apply the query and filters, then sort each
+    // line by its own stat (or by weights) and refresh the pictures
+    fn sort_lines(&mut self) -> Command<Message> {
+        if self.map.is_empty() {
+            return Command::none();
+        }
 
-        GUI {
-            map: HashMap::new(),
-            backline: Vec::new(),
-            subline: Vec::new(),
-            frontline: Vec::new(),
-            import_all: false,
-            frontline_sort: SortChoice::HP,
-            subline_sort: SortChoice::HP,
-            backline_sort: SortChoice::HP,
-            frontline_class_filter: None,
-            backline_class_filter: None,
-            subline_class_filter: None,
-            backline_img: vec![image_test.clone(); 3],
-            frontline_img: vec![image_test.clone(); 3],
-            subline_img: vec![image_test.clone(); 3],
+        let query = match self.query_input.trim() {
+            "" => None,
+            query => match query.parse::<Expr>() {
+                Ok(query) => Some(query),
+                Err(e) => {
+                    self.query_error = Some(e.to_string());
+                    return Command::none();
+                }
+            },
+        };
+        self.query_error = None;
+        let ships: HashMap<i32, Ship> = self
+            .map
+            .iter()
+            .filter(|(_, ship)| query.as_ref().is_none_or(|q| q.matches(ship)))
+            .filter(|(_, ship)| self.nation_filter.as_ref().is_none_or(|n| ship.nation == *n))
+            .filter(|(_, ship)| self.rarity_filter.as_ref().is_none_or(|r| ship.rarity == *r))
+            .map(|(id, ship)| (*id, ship.clone()))
+            .collect();
+        (self.backline, self.frontline, self.subline) = find_line(&ships);
+
+        for (line, class) in [
+            (&mut self.backline, &self.backline_class_filter),
+            (&mut self.frontline, &self.frontline_class_filter),
+            (&mut self.subline, &self.subline_class_filter),
+        ] {
+            if let Some(class) = class {
+                *line = filter_line_class(line, class.clone()).into_iter().cloned().collect();
+            }
+            if let Some(armor) = &self.armor_filter {
+                *line = filter_line_armor(line, armor.clone()).into_iter().cloned().collect();
+            }
         }
+
+        self.scorer = None;
+        self.weights_error = None;
+        self.tie_breakers_error = None;
+        if self.use_weights {
+            match self.weights_input.parse::<Weights>() {
+                Ok(weights) => {
+                    let normalizer = Normalizer::new(self.map.values(), self.normalization);
+                    let scorer = Scorer::new(weights, normalizer);
+                    for line in [&mut self.backline, &mut self.frontline, &mut self.subline] {
+                        sort_ships_weighted(line, &scorer);
+                    }
+                    self.scorer = Some(scorer);
+                }
+                Err(e) => self.weights_error = Some(e.to_string()),
+            }
+        } else {
+            match SortKey::parse_chain(&self.tie_breakers) {
+                Ok(then_by) => {
+                    for (line, stat, direction) in [
+                        (&mut self.backline, self.backline_sort, self.backline_direction),
+                        (&mut self.frontline, self.frontline_sort, self.frontline_direction),
+                        (&mut self.subline, self.subline_sort, self.subline_direction),
+                    ] {
+                        let mut keys = vec![SortKey::new(stat, direction)];
+                        keys.extend(then_by.iter().copied());
+                        sort_ships(line, &keys);
+                    }
+                }
+                Err(e) => self.tie_breakers_error = Some(e.to_string()),
+            }
+        }
+
+        self.load_images()
     }
 
-    fn title(&self) -> String {
-        String::from("Azur Lane Sorter")
+    // Load the cached pictures of the ships near the top of each line and
+    // return the ones that don't have one yet. Those are the ones on screen
+    // without scrolling, so they are the only ones worth reading from disk or
+    // downloading.
+    fn show_images(&mut self) -> Vec<Ship> {
+        let wanted = self.top_count.max(PREVIEW_COUNT);
+        let mut missing = Vec::new();
+        for line in [&self.backline, &self.frontline, &self.subline] {
+            for ship in line.iter().take(wanted) {
+                if !ship.image.is_empty() && self.images.cached(ship).is_none() {
+                    missing.push(ship.clone());
+                }
+            }
+        }
+        missing
     }
 
-    fn update(&mut self, message: Message) {
-        match message {
-            // TODO: add more stuff here
-            Message::SortShips => {
-
-                if self.map.len() > 0
-                {
-                    (self.backline, self.frontline, self.subline) = find_line(&self.map);
-                    // TODO: use controls to actually sort rather than just putting them in the
-                    // lines
-                    println!("{:?}", self.backline[0]);
-                    println!("{:?}", self.backline[1]);
-                    println!("{:?}", self.backline[2]);
-
-                    for i in 0..3 {
-                        self.backline_img[i] = self.backline[i].retrieve_img();
-                    }
+    // Show cached pictures and download the missing ones in the background
+    fn load_images(&mut self) -> Command<Message> {
+        let missing = self.show_images();
+        // Pictures are not worth interrupting an import or scrape for
+        let busy = self.task.as_ref().is_some_and(|task| task.kind != TaskKind::Images);
+        if missing.is_empty() || busy || self.images.is_offline() {
+            return Command::none();
+        }
 
-                    println!("{:?}", self.frontline[0]);
-                    println!("{:?}", self.frontline[1]);
-                    println!("{:?}", self.frontline[2]);
+        let (id, cancelled) = self.start_task(TaskKind::Images, missing.len());
+        let dir = self.images.dir().to_path_buf();
+        // One download at a time, so a cancel stops the ones still to come
+        let (sender, receiver) = mpsc::unbounded();
+        thread::spawn(move || {
+            for ship in missing {
+                if cancelled.load(Ordering::Relaxed) {
+                    break;
+                }
+                let result = download_image(&dir, &ship);
+                let unreachable = matches!(result, Err(ImageError::Unreachable(_)));
+                if sender.unbounded_send((ship.id, result)).is_err() || unreachable {
+                    break;
+                }
+            }
+        });
+        Command::run(receiver, move |(ship_id, result)| Message::ImageLoaded(id, ship_id, result))
+    }
 
-                    for i in 0..3 {
-                        self.frontline_img[i] = self.frontline[i].retrieve_img();
-                    }
+    // Start a background task in place of whatever was running
+    fn start_task(&mut self, kind: TaskKind, total: usize) -> (u64, Arc<AtomicBool>) {
+        self.cancel_task();
+        self.next_task += 1;
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.task = Some(Task {
+            id: self.next_task,
+            kind,
+            done: 0,
+            total,
+            cancelled: cancelled.clone(),
+        });
+        self.status = format!("{}...", kind);
+        (self.next_task, cancelled)
+    }
 
-                    println!("{:?}", self.subline[0]);
-                    println!("{:?}", self.subline[1]);
-                    println!("{:?}", self.subline[2]);
+    fn cancel_task(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.cancelled.store(true, Ordering::Relaxed);
+            self.status = format!("{} cancelled", task.kind);
+        }
+    }
 
-                    for i in 0..3 {
-                        self.subline_img[i] = self.subline[i].retrieve_img();
-                    }
+    // Whether a result comes from the task that is still running
+    fn is_current(&self, id: u64) -> bool {
+        self.task.as_ref().is_some_and(|task| task.id == id)
+    }
+
+    fn import(&mut self) -> Command<Message> {
+        let (id, _) = self.start_task(TaskKind::Import, 0);
+        let import_all = self.import_all;
+        Command::perform(in_background(move || import_ships(import_all).map_err(|e| e.to_string())), move |result| {
+            Message::Imported(id, result)
+        })
+    }
+
+    fn scrape(&mut self) -> Command<Message> {
+        let (id, cancelled) = self.start_task(TaskKind::Scrape, 0);
+        Command::perform(in_background(move || scrape_ships(&cancelled).map_err(|e| e.to_string())), move |result| {
+            Message::Scraped(id, result)
+        })
+    }
+
+    fn image_loaded(&mut self, ship_id: &str, result: Result<PathBuf, ImageError>) {
+        match result {
+            Ok(path) => {
+                self.images.insert(ship_id, path);
+            }
+            // The wiki can't be reached, so the other downloads would fail too
+            Err(ImageError::Unreachable(e)) => {
+                eprintln!("Could not download images, using placeholders: {}", e);
+                self.images.set_offline();
+                self.task = None;
+                self.status = String::from("Offline, showing placeholder images");
+                return;
+            }
+            // Only this ship's picture, which is tried again next time
+            Err(e) => {
+                let name = self.map.values().find(|ship| ship.id == ship_id).map_or(ship_id, |ship| &ship.name);
+                eprintln!("Could not download the image for {}: {}", name, e);
+            }
+        }
+
+        if let Some(task) = &mut self.task {
+            task.done += 1;
+            if task.done >= task.total {
+                self.task = None;
+                self.status = String::from("Images loaded");
+            }
+        }
+        self.show_images();
+    }
+
+    // A ship's name that opens its detail panel
+    fn ship_button(&self, ship: &Ship) -> Button<'_, Message> {
+        Button::new(text(self.ship_label(ship)))
+            .on_press(Message::SelectShip(ship.id.clone()))
+            .style(iced::theme::Button::Text)
+    }
+
+    // The selected ship and the line it is in. Ships the filters hide are
+    // still found in the loaded map, just without a line to rank them in.
+    fn selected_ship(&self) -> Option<(&Ship, Option<Line>)> {
+        let id = self.selected.as_ref()?;
+        Line::ALL
+            .iter()
+            .find_map(|&line| self.line(line).iter().find(|s| &s.id == id).map(|s| (s, Some(line))))
+            .or_else(|| self.map.values().find(|s| &s.id == id).map(|s| (s, None)))
+    }
+
+    // Each stat scaled between the weakest and strongest ship of the same
+    // class, flipped for stats where lower is better. Stats every ship of
+    // the class has the same value for (oxygen outside subs, ...) are left
+    // out, as is armor.
+    fn radar_axes(&self, ship: &Ship) -> Vec<(String, f32)> {
+        let class: Vec<&Ship> = self.map.values().filter(|s| s.class == ship.class).collect();
+        let normalizer = Normalizer::for_class(self.map.values(), &ship.class, Normalization::MinMax);
 
+        SortChoice::all()
+            .into_iter()
+            .filter(|&stat| stat != SortChoice::Armor)
+            .filter(|&stat| class.iter().any(|s| stat.value(s) != stat.value(ship)))
+            .map(|stat| {
+                let value = normalizer.normalize(ship, stat) as f32;
+                let value = match stat.default_direction() {
+                    SortDirection::Descending => value,
+                    SortDirection::Ascending => 1.0 - value,
+                };
+                (stat.name().to_string(), value)
+            })
+            .collect()
+    }
+
+    fn update_radar(&mut self) {
+        let axes = self.selected_ship().map(|(ship, _)| self.radar_axes(ship));
+        self.radar = RadarChart::new(axes.unwrap_or_default());
+    }
+
+    // Everything about the selected ship, its stats against its class and
+    // where it places in its line for each stat
+    fn detail_view(&self) -> Option<iced::Element<'_, Message>> {
+        let (ship, line) = self.selected_ship()?;
+        let line_ships = line.map(|line| self.line(line));
+
+        let stats = column(SortChoice::all().into_iter().map(|stat| {
+            let value = match stat {
+                SortChoice::Armor => ship.armor.to_string(),
+                _ if stat.value(ship) != stat.base_value(ship) => {
+                    format!("{} (base {})", stat.value(ship), stat.base_value(ship))
+                }
+                _ => stat.value(ship).to_string(),
+            };
+            let rank = match (line, line_ships) {
+                (Some(line), Some(ships)) => format!("#{} of {} in {}", stat.rank(ship, ships), ships.len(), line),
+                _ => String::new(),
+            };
+            row![
+                text(stat.name()).width(Length::Fixed(110.0)),
+                text(value).width(Length::Fixed(120.0)),
+                text(rank),
+            ]
+            .into()
+        }));
+
+        let mut info = column![
+            text(format!("{} ({}, {} armor)", ship.nation, ship.rarity, ship.armor)),
+            text(format!("Id {}", ship.id)),
+        ];
+        if let Some(affinity) = ship.affinity {
+            info = info.push(text(format!("Affinity: {}", affinity)));
+        }
+        if !ship.gear.is_empty() {
+            let gear: Vec<&str> = ship.gear.iter().map(|g| g.name.as_str()).collect();
+            info = info.push(text(format!("Gear: {}", gear.join(", "))));
+        }
+        for skill in &ship.skills {
+            info = info.push(text(format!("Skill: {}", skill)));
+        }
+
+        let chart = canvas(&self.radar)
+            .width(Length::Fixed(320.0))
+            .height(Length::Fixed(320.0));
+
+        Some(
+            column![
+                row![
+                    image::viewer(self.images.peek(ship)).width(Length::Fixed(64.0)),
+                    text(format!("{} - {}", ship, ship.class)).size(24),
+                    Button::new("Compare").on_press(Message::AddToCompare(ship.id.clone())),
+                    Button::new("Close").on_press(Message::CloseDetail),
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+                row![column![info, stats].spacing(10), column![text(format!("Against other {}s", ship.class)), chart]]
+                    .spacing(20),
+            ]
+            .spacing(10)
+            .padding(10)
+            .into(),
+        )
+    }
+
+    // Ships added for comparison lined up stat by stat, with the difference
+    // from the first ship and the best value of each stat highlighted
+    fn compare_view(&self) -> iced::Element<'_, Message> {
+        const LABEL: f32 = 110.0;
+        const CELL: f32 = 160.0;
+
+        let ships: Vec<Ship> = self
+            .compare_ids
+            .iter()
+            .filter_map(|id| self.map.values().find(|s| &s.id == id).cloned())
+            .collect();
+        if ships.len() < 2 {
+            return text("Open a ship's details and press Compare to add it here. Add at least two ships.").into();
+        }
+
+        let current = Comparison::new(ships);
+        let levels = current.levels();
+
+        // The controls come first so a level that stopped applying can be
+        // switched back
+        let mut controls = row![
+            Button::new("Current stats").on_press(Message::CompareLevel(None)),
+            Button::new("Clear").on_press(Message::ClearCompare),
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center);
+        // Levels only show up when multi-level data is loaded for every ship
+        if !levels.is_empty() {
+            controls = controls.push(text("At")).push(pick_list(levels, self.compare_level, |level| {
+                Message::CompareLevel(Some(level))
+            }));
+        }
+
+        let comparison = match self.compare_level {
+            Some(level) => match current.at_level(level) {
+                Some(at_level) => at_level,
+                None => return column![controls, text(format!("Not every ship has stats for {}", level))].spacing(10).into(),
+            },
+            None => current,
+        };
+
+        let field_row = |label: &str, field: fn(&Ship) -> String| {
+            let mut cells = row![text(label).width(Length::Fixed(LABEL))];
+            for ship in &comparison.ships {
+                cells = cells.push(text(field(ship)).width(Length::Fixed(CELL)));
+            }
+            iced::Element::from(cells)
+        };
+
+        let mut names = row![text("").width(Length::Fixed(LABEL))];
+        for ship in &comparison.ships {
+            names = names.push(
+                row![
+                    text(ship.to_string()),
+                    Button::new("x").on_press(Message::RemoveFromCompare(ship.id.clone())).style(iced::theme::Button::Text),
+                ]
+                .align_items(Alignment::Center)
+                .width(Length::Fixed(CELL)),
+            );
+        }
+
+        let winner = iced::Color::from_rgb(0.4, 0.85, 0.4);
+        let mut table = column![
+            names,
+            field_row("Class", |s| s.class.to_string()),
+            field_row("Nation", |s| s.nation.to_string()),
+            field_row("Rarity", |s| s.rarity.to_string()),
+        ];
+        for stat_row in &comparison.rows {
+            let mut cells = row![text(stat_row.stat.name()).width(Length::Fixed(LABEL))];
+            for index in 0..comparison.ships.len() {
+                let cell = text(comparison.cell(stat_row, index)).width(Length::Fixed(CELL));
+                let cell = if stat_row.is_winner(index) { cell.style(winner) } else { cell };
+                cells = cells.push(cell);
+            }
+            table = table.push(cells);
+        }
+        let mut wins = row![text("Stats won").width(Length::Fixed(LABEL))];
+        for count in comparison.wins() {
+            wins = wins.push(text(count).width(Length::Fixed(CELL)));
+        }
+
+        column![controls, scrollable(table.push(wins).spacing(4))].spacing(10).into()
+    }
+
+    // The top ships of a line with their pictures
+    fn line_preview(&self, line: Line) -> iced::widget::Row<'_, Message> {
+        row(self
+            .line(line)
+            .iter()
+            .take(PREVIEW_COUNT)
+            .flat_map(|ship| [image::viewer(self.images.peek(ship)).into(), self.ship_button(ship).into()])
+            .collect::<Vec<iced::Element<'_, Message>>>())
+    }
+
+    // Every ship in one line with all its stats. Clicking a stat header sorts
+    // the line by it (again to flip the direction) and the top rows are
+    // highlighted.
+    fn table_view(&self) -> iced::Element<'_, Message> {
+        const ICON: f32 = 32.0;
+        const NAME: f32 = 180.0;
+        const CELL: f32 = 90.0;
+
+        let (sort, direction) = self.line_sort(self.table_line);
+        // Weighted sorts don't follow any one column
+        let arrow = |choice: SortChoice| {
+            if choice != sort || self.scorer.is_some() {
+                ""
+            } else if direction == SortDirection::Descending {
+                " v"
+            } else {
+                " ^"
+            }
+        };
+
+        let mut header = row![
+            text("").width(Length::Fixed(ICON)),
+            text("Name").width(Length::Fixed(NAME)),
+            text("Class").width(Length::Fixed(CELL / 2.0)),
+            text("Rarity").width(Length::Fixed(CELL)),
+        ];
+        for choice in SortChoice::all() {
+            header = header.push(
+                Button::new(text(format!("{}{}", choice, arrow(choice))).size(12))
+                    .on_press(Message::TableSort(choice))
+                    .width(Length::Fixed(CELL)),
+            );
+        }
+
+        let line = self.line(self.table_line);
+        let top: Vec<&str> = line.iter().take(self.top_count).map(|ship| ship.id.as_str()).collect();
+        let ship_row = |ship: &Ship| {
+            let mut cells = row![
+                image::Image::new(self.images.peek(ship))
+                    .width(Length::Fixed(ICON))
+                    .height(Length::Fixed(ICON)),
+                self.ship_button(ship).width(Length::Fixed(NAME)),
+                text(&ship.class).width(Length::Fixed(CELL / 2.0)),
+                text(&ship.rarity).width(Length::Fixed(CELL)),
+            ]
+            .align_items(Alignment::Center);
+            for choice in SortChoice::all() {
+                let value = match choice {
+                    SortChoice::Armor => ship.armor.to_string(),
+                    _ => choice.value(ship).to_string(),
+                };
+                cells = cells.push(text(value).width(Length::Fixed(CELL)));
+            }
+
+            let cells = container(cells).width(Length::Fill);
+            let cells = if top.contains(&ship.id.as_str()) { cells.style(iced::theme::Container::Box) } else { cells };
+            iced::Element::from(cells)
+        };
+
+        // Groups keep the line's order inside them
+        let groups: Vec<(String, Vec<&Ship>)> = match self.table_group {
+            GroupBy::Nothing => vec![(String::new(), line.iter().collect())],
+            GroupBy::Nation => labelled(group_ships(line, |s| s.nation.clone())),
+            GroupBy::Rarity => labelled(group_ships(line, |s| s.rarity.clone())),
+            GroupBy::Class => labelled(group_ships(line, |s| s.class.clone())),
+        };
+        let mut rows = column![];
+        for (label, ships) in groups {
+            if self.table_group != GroupBy::Nothing {
+                rows = rows.push(text(format!("{} ({})", label, ships.len())).size(18));
+            }
+            for ship in ships {
+                rows = rows.push(ship_row(ship));
+            }
+        }
+
+        column![
+            row![
+                text("Line"),
+                pick_list(&Line::ALL[..], Some(self.table_line), Message::TableLine),
+                text("Highlight top"),
+                pick_list(&TOP_COUNTS[..], Some(self.top_count), Message::TopCount),
+                text("Group by"),
+                pick_list(&GroupBy::ALL[..], Some(self.table_group), Message::TableGroup),
+                text(format!("{} ships", line.len())),
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
+            scrollable(column![header, rows]).direction(scrollable::Direction::Both {
+                vertical: scrollable::Properties::default(),
+                horizontal: scrollable::Properties::default(),
+            }),
+        ]
+        .into()
+    }
+
+    // What is running, how far along it is and a way to stop it
+    fn status_view(&self) -> iced::Element<'_, Message> {
+        let progress = self.task.as_ref().filter(|task| task.total > 0).map(|task| {
+            progress_bar(0.0..=task.total as f32, task.done as f32)
+                .width(Length::Fixed(200.0))
+                .height(Length::Fixed(10.0))
+        });
+
+        row![text(&self.status)]
+            .push_maybe(progress)
+            .push_maybe(self.task.as_ref().map(|_| Button::new("Cancel").on_press(Message::CancelTask)))
+            .spacing(10)
+            .align_items(Alignment::Center)
+            .into()
+    }
+
+    fn allocate(&self) -> Result<Vec<Fleet>, Box<dyn std::error::Error>> {
+        let weights: Weights = self.weights_input.parse()?;
+        let scorer = Scorer::new(weights, Normalizer::new(self.map.values(), self.normalization));
+        let max_cost = self.fleet_max_cost.trim();
+
+        let constraints = FleetConstraints {
+            min_classes: FleetConstraints::parse_min_classes(&self.fleet_min_classes)?,
+            max_cost: if max_cost.is_empty() { None } else { Some(max_cost.parse()?) },
+            ..FleetConstraints::default()
+        };
+
+        Ok(allocate_fleets(&self.map, self.fleet_count, &scorer, &constraints, self.allocation_mode)?)
+    }
+
+    fn fleets_view(&self) -> iced::Element<'_, Message> {
+        let results = match &self.fleet_error {
+            Some(error) => row![text(error)],
+            None => row(self
+                .fleets
+                .iter()
+                .enumerate()
+                .map(|(index, fleet)| column![text(format!("Fleet {}", index + 1)), text(fleet.to_string())])
+                .map(iced::Element::from)
+                .collect::<Vec<_>>())
+            .spacing(20),
+        };
+
+        column![
+            row![
+                text("Objective"),
+                text_input("torpedo=1.0, reload=0.6", &self.weights_input).on_input(Message::WeightsInput),
+                pick_list(&Normalization::ALL[..], Some(self.normalization), Message::NormalizationPick),
+            ],
+            row![
+                text("Fleets"),
+                pick_list(&FLEET_COUNTS[..], Some(self.fleet_count), Message::FleetCountPick),
+                text("Optimize"),
+                pick_list(&AllocationMode::ALL[..], Some(self.allocation_mode), Message::AllocationModePick),
+            ],
+            row![
+                text("Max cost"),
+                text_input("none", &self.fleet_max_cost).on_input(Message::FleetMaxCostInput),
+                text("Min classes"),
+                text_input("CV, DD=2", &self.fleet_min_classes).on_input(Message::FleetMinClassesInput),
+            ],
+            Button::new("Allocate Fleets")
+                .on_press(Message::AllocateFleets)
+                .width(Length::Fill)
+                .padding(10),
+            results,
+        ]
+        .into()
+    }
+}
+
+impl Application for GUI {
+    type Executor = executor::Default;
+    type Message = Message;
+    type Theme = iced::Theme;
+    type Flags = ();
+
+    fn theme(&self) -> iced::Theme {
+        iced::Theme::Dark
+    }
+
+    fn new(_flags: ()) -> (Self, Command<Message>) {
+        (GUI::new(), Command::none())
+    }
+
+    fn title(&self) -> String {
+        String::from("Azur Lane Sorter")
+    }
+
+    fn update(&mut self, message: Message) -> Command<Message> {
+        match message {
+            // TODO: add more stuff here
+            Message::SortShips => return self.sort_lines(),
+            Message::ImportShips => return self.import(),
+            Message::ScrapeWiki => return self.scrape(),
+            Message::CancelTask => self.cancel_task(),
+            Message::Imported(id, result) if self.is_current(id) => {
+                self.task = None;
+                match result {
+                    Ok(map) => {
+                        self.status = format!("Imported {} ships", map.len());
+                        self.map = map;
+                        self.update_radar();
+                    }
+                    Err(e) => self.status = format!("Import failed: {}", e),
                 }
             }
-            Message::ImportShips => {
-                self.map = read_ships_from_file("data_export.json").unwrap();
+            Message::Scraped(id, result) if self.is_current(id) => {
+                self.task = None;
+                match result {
+                    Ok((map, summary)) => {
+                        self.status = format!("Scraped {} ships ({})", map.len(), summary);
+                        self.map = map;
+                        self.update_radar();
+                    }
+                    Err(e) => self.status = format!("Scrape failed: {}", e),
+                }
             }
+            Message::ImageLoaded(id, ship_id, result) if self.is_current(id) => self.image_loaded(&ship_id, result),
+            // Left over from a cancelled task
+            Message::Imported(..) | Message::Scraped(..) | Message::ImageLoaded(..) => {}
             Message::ClearLines => {
                 // Reset the lines but don't clear the map
                 self.backline = Vec::new();
                 self.frontline = Vec::new();
                 self.subline = Vec::new();
             }
-            Message::FrontlineSort(choice) => self.frontline_sort = choice,
-            Message::BacklineSort(choice) => self.backline_sort = choice,
-            Message::SublineSort(choice) => self.subline_sort = choice,
+            // Sort and filter controls re-sort straight away
+            Message::FrontlineSort(choice) => {
+                self.frontline_sort = choice;
+                self.frontline_direction = choice.default_direction();
+                return self.sort_lines();
+            }
+            Message::BacklineSort(choice) => {
+                self.backline_sort = choice;
+                self.backline_direction = choice.default_direction();
+                return self.sort_lines();
+            }
+            Message::SublineSort(choice) => {
+                self.subline_sort = choice;
+                self.subline_direction = choice.default_direction();
+                return self.sort_lines();
+            }
+            Message::FrontlineDirection(direction) => {
+                self.frontline_direction = direction;
+                return self.sort_lines();
+            }
+            Message::BacklineDirection(direction) => {
+                self.backline_direction = direction;
+                return self.sort_lines();
+            }
+            Message::SublineDirection(direction) => {
+                self.subline_direction = direction;
+                return self.sort_lines();
+            }
+            Message::FrontlineClassFilter(class) => {
+                self.frontline_class_filter = Some(class);
+                return self.sort_lines();
+            }
+            Message::BacklineClassFilter(class) => {
+                self.backline_class_filter = Some(class);
+                return self.sort_lines();
+            }
+            Message::SublineClassFilter(class) => {
+                self.subline_class_filter = Some(class);
+                return self.sort_lines();
+            }
+            Message::ArmorFilter(armor) => {
+                self.armor_filter = Some(armor);
+                return self.sort_lines();
+            }
+            Message::NationFilter(nation) => {
+                self.nation_filter = Some(nation);
+                return self.sort_lines();
+            }
+            Message::RarityFilter(rarity) => {
+                self.rarity_filter = Some(rarity);
+                return self.sort_lines();
+            }
+            Message::ClearFilters => {
+                self.frontline_class_filter = None;
+                self.backline_class_filter = None;
+                self.subline_class_filter = None;
+                self.armor_filter = None;
+                self.nation_filter = None;
+                self.rarity_filter = None;
+                return self.sort_lines();
+            }
+            Message::TableLine(line) => self.table_line = line,
+            Message::TableGroup(group) => self.table_group = group,
+            Message::SelectShip(id) => {
+                self.selected = Some(id);
+                self.update_radar();
+            }
+            Message::CloseDetail => {
+                self.selected = None;
+                self.update_radar();
+            }
+            Message::AddToCompare(id) => {
+                if !self.compare_ids.contains(&id) {
+                    self.compare_ids.push(id);
+                }
+            }
+            Message::RemoveFromCompare(id) => self.compare_ids.retain(|other| *other != id),
+            Message::ClearCompare => self.compare_ids.clear(),
+            Message::CompareLevel(level) => self.compare_level = level,
+            Message::TopCount(count) => {
+                self.top_count = count;
+                return self.load_images();
+            }
+            Message::TableSort(choice) => {
+                // A header click is a request to sort by that stat, not by weights
+                self.use_weights = false;
+                let (sort, direction) = self.line_sort_mut(self.table_line);
+                if *sort == choice {
+                    *direction = match direction {
+                        SortDirection::Descending => SortDirection::Ascending,
+                        SortDirection::Ascending => SortDirection::Descending,
+                    };
+                } else {
+                    *sort = choice;
+                    *direction = choice.default_direction();
+                }
+                return self.sort_lines();
+            }
+            Message::TieBreakersInput(input) => self.tie_breakers = input,
+            Message::QueryInput(input) => self.query_input = input,
             Message::ImportAllToggle(toggle) => self.import_all = toggle,
-            Message::FrontlineClassFilter(class) => self.frontline_class_filter = Some(class),
-            Message::BacklineClassFilter(class) => self.backline_class_filter = Some(class),
-            Message::SublineClassFilter(class) => self.subline_class_filter = Some(class),
+            Message::UseWeightsToggle(toggle) => self.use_weights = toggle,
+            Message::WeightsInput(input) => self.weights_input = input,
+            Message::NormalizationPick(mode) => self.normalization = mode,
+            Message::ShowView(view) => self.view = view,
+            Message::FleetCountPick(count) => self.fleet_count = count,
+            Message::AllocationModePick(mode) => self.allocation_mode = mode,
+            Message::FleetMaxCostInput(input) => self.fleet_max_cost = input,
+            Message::FleetMinClassesInput(input) => self.fleet_min_classes = input,
+            Message::AllocateFleets => match self.allocate() {
+                Ok(fleets) => {
+                    self.fleets = fleets;
+                    self.fleet_error = None;
+                }
+                Err(e) => self.fleet_error = Some(e.to_string()),
+            },
         }
+        Command::none()
     }
 
     fn view(&self) -> iced::Element<'_, Self::Message> {
@@ -182,6 +1080,10 @@ impl Sandbox for GUI {
                 .on_press(Message::ImportShips)
                 .width(Length::Fill)
                 .padding(10),
+            Button::new("Scrape Wiki")
+                .on_press(Message::ScrapeWiki)
+                .width(Length::Fill)
+                .padding(10),
             Button::new("Sort Ships")
                 .on_press(Message::SortShips)
                 .width(Length::Fill)
@@ -194,19 +1096,62 @@ impl Sandbox for GUI {
         .align_items(Alignment::Center)
         .width(Length::Fill);
 
+        let views = row![
+            Button::new("Lines").on_press(Message::ShowView(View::Lines)),
+            Button::new("Table").on_press(Message::ShowView(View::Table)),
+            Button::new(text(format!("Compare ({})", self.compare_ids.len()))).on_press(Message::ShowView(View::Compare)),
+            Button::new("Fleets").on_press(Message::ShowView(View::Fleets)),
+        ]
+        .spacing(10);
+
+        if self.view == View::Fleets {
+            return column![views, self.status_view(), controls, self.fleets_view()].into();
+        }
+        if self.view == View::Compare {
+            return column![views, self.status_view(), self.compare_view()].into();
+        }
+        if self.view == View::Table {
+            return column![views, self.status_view(), controls]
+                .push_maybe(self.detail_view())
+                .push(self.table_view())
+                .into();
+        }
 
         column![
+            views,
+            self.status_view(),
             controls,
             Checkbox::new("Import All", self.import_all).on_toggle(Message::ImportAllToggle),
             row![
-                text("Backline"),
-                image::viewer(self.backline_img[0].clone()),
-                text("Test"),
-                image::viewer(self.backline_img[1].clone()),
-                text("Test"),
-                image::viewer(self.backline_img[2].clone()),
-                text("Test"),
-                pick_list(&Class::BACK[..], self.backline_class_filter.clone(), Message::BacklineClassFilter)
+                Checkbox::new("Sort by weights", self.use_weights).on_toggle(Message::UseWeightsToggle),
+                text_input("torpedo=1.0, reload=0.6", &self.weights_input).on_input(Message::WeightsInput),
+                pick_list(&Normalization::ALL[..], Some(self.normalization), Message::NormalizationPick),
+                text(self.weights_error.clone().unwrap_or_default()),
+            ],
+            row![
+                text("Then by"),
+                text_input("cost:asc, luck", &self.tie_breakers).on_input(Message::TieBreakersInput),
+                text(self.tie_breakers_error.clone().unwrap_or_default()),
+            ],
+            row![
+                text("Armor"),
+                pick_list(&Armor::ALL[..], self.armor_filter.clone(), Message::ArmorFilter),
+                text("Nation"),
+                pick_list(self.choices(|s| &s.nation), self.nation_filter.clone(), Message::NationFilter),
+                text("Rarity"),
+                pick_list(self.choices(|s| &s.rarity), self.rarity_filter.clone(), Message::RarityFilter),
+                Button::new("Clear filters").on_press(Message::ClearFilters),
+            ],
+            row![
+                text("Where"),
+                text_input("class in (CV, CVL) and aviation > 350", &self.query_input).on_input(Message::QueryInput),
+                text(self.query_error.clone().unwrap_or_default()),
+            ],
+            row![
+                text(Line::Main.to_string()),
+                self.line_preview(Line::Main),
+                pick_list(&Class::BACK[..], self.backline_class_filter.clone(), Message::BacklineClassFilter),
+                pick_list(&SortDirection::ALL[..], Some(self.backline_direction), Message::BacklineDirection),
             ],
             row(SortChoice::all()
                 .iter()
@@ -222,15 +1167,14 @@ impl Sandbox for GUI {
                 .map(iced::Element::from)
                 .collect::<Vec<_>>()),
             row![
-                text("Frontline"),
-                image::viewer(self.frontline_img[0].clone()),
-                image::viewer(self.frontline_img[1].clone()),
-                image::viewer(self.frontline_img[2].clone()),
+                text(Line::Vanguard.to_string()),
+                self.line_preview(Line::Vanguard),
                 pick_list(
                     &Class::FRONT[..],
                     self.frontline_class_filter.clone(),
                     Message::FrontlineClassFilter
-                )
+                ),
+                pick_list(&SortDirection::ALL[..], Some(self.frontline_direction), Message::FrontlineDirection),
             ],
             row(SortChoice::all()
                 .iter()
@@ -246,15 +1190,14 @@ impl Sandbox for GUI {
                 .map(iced::Element::from)
                 .collect::<Vec<_>>()),
             row![
-                text("Subline"),
-                image::viewer(self.subline_img[0].clone()),
-                image::viewer(self.subline_img[1].clone()),
-                image::viewer(self.subline_img[2].clone()),
+                text(Line::Subs.to_string()),
+                self.line_preview(Line::Subs),
                 pick_list(
                     &Class::SUB[..],
                     self.subline_class_filter.clone(),
                     Message::SublineClassFilter
-                )
+                ),
+                pick_list(&SortDirection::ALL[..], Some(self.subline_direction), Message::SublineDirection),
             ],
             row(SortChoice::all()
                 .iter()
@@ -270,6 +1213,7 @@ impl Sandbox for GUI {
                 .map(iced::Element::from)
                 .collect::<Vec<_>>()),
         ]
+        .push_maybe(self.detail_view())
         .into()
     }
 }
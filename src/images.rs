@@ -0,0 +1,150 @@
+use core::fmt;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use iced::widget::image;
+
+use crate::scrape::parse_file_page;
+use crate::ship::Ship;
+
+pub const DEFAULT_IMAGE_DIR: &str = "images";
+pub const PLACEHOLDER_IMAGE: &str = "test.png";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImageError {
+    NoImage,             // the ship has no image address to download
+    Unreachable(String), // the wiki could not be reached at all
+    Failed(String),      // this ship's image could not be fetched or saved
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImageError::NoImage => write!(f, "no image listed"),
+            ImageError::Unreachable(e) | ImageError::Failed(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for ImageError {}
+
+impl From<reqwest::Error> for ImageError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_connect() || e.is_timeout() {
+            ImageError::Unreachable(e.to_string())
+        } else {
+            ImageError::Failed(e.to_string())
+        }
+    }
+}
+
+impl From<io::Error> for ImageError {
+    fn from(e: io::Error) -> Self {
+        ImageError::Failed(e.to_string())
+    }
+}
+
+// Where a ship's icon is kept in the cache. Ids come from the wiki, so only
+// keep characters that are safe in a file name.
+pub fn cache_path(dir: &Path, ship: &Ship) -> PathBuf {
+    let id: String = ship
+        .id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    dir.join(format!("{}.png", id))
+}
+
+// The address of the image itself. Scraped ships point at the wiki's
+// `File:` page, which has to be fetched to find the real file.
+fn resolve_image_url(url: &str) -> Result<String, ImageError> {
+    if !url.contains("/File:") {
+        return Ok(url.to_string());
+    }
+    let page = reqwest::blocking::get(url)?.error_for_status()?.text()?;
+    parse_file_page(&page, url).ok_or_else(|| ImageError::Failed(format!("no image on {}", url)))
+}
+
+// Download a ship's icon into the cache and return where it was saved
+pub fn download_image(dir: &Path, ship: &Ship) -> Result<PathBuf, ImageError> {
+    if ship.image.is_empty() {
+        return Err(ImageError::NoImage);
+    }
+    let url = resolve_image_url(&ship.image)?;
+    let bytes = reqwest::blocking::get(&url)?.error_for_status()?.bytes()?;
+
+    fs::create_dir_all(dir)?;
+    let path = cache_path(dir, ship);
+    // Write to a temporary file first so an interrupted download never
+    // leaves a broken image in the cache
+    let partial = path.with_extension("part");
+    fs::write(&partial, &bytes)?;
+    fs::rename(&partial, &path)?;
+    Ok(path)
+}
+
+// Ship icons for the GUI, kept on disk by ship id and in memory once loaded.
+// Downloads happen elsewhere (the GUI runs them in the background) and are
+// handed back with `insert`. Once the wiki can't be reached the cache is
+// marked offline so the rest of the ships get the placeholder straight away;
+// a ship whose own image fails is just tried again next time.
+pub struct ImageCache {
+    dir: PathBuf,
+    handles: HashMap<String, image::Handle>,
+    placeholder: image::Handle,
+    offline: bool,
+}
+
+impl ImageCache {
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        ImageCache {
+            dir: dir.as_ref().to_path_buf(),
+            handles: HashMap::new(),
+            placeholder: image::Handle::from_path(PLACEHOLDER_IMAGE),
+            offline: false,
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn placeholder(&self) -> image::Handle {
+        self.placeholder.clone()
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.offline
+    }
+
+    pub fn set_offline(&mut self) {
+        self.offline = true;
+    }
+
+    // The ship's icon if it has been loaded or is already on disk
+    pub fn cached(&mut self, ship: &Ship) -> Option<image::Handle> {
+        if let Some(handle) = self.handles.get(&ship.id) {
+            return Some(handle.clone());
+        }
+        let path = cache_path(&self.dir, ship);
+        if !path.is_file() {
+            return None;
+        }
+        Some(self.insert(&ship.id, path))
+    }
+
+    // The loaded icon or the placeholder, for drawing without touching the disk
+    pub fn peek(&self, ship: &Ship) -> image::Handle {
+        self.handles.get(&ship.id).cloned().unwrap_or_else(|| self.placeholder())
+    }
+
+    // Record an icon that has just been downloaded
+    pub fn insert(&mut self, id: &str, path: PathBuf) -> image::Handle {
+        let handle = image::Handle::from_path(path);
+        self.handles.insert(id.to_string(), handle.clone());
+        handle
+    }
+}
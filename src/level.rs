@@ -0,0 +1,182 @@
+// Estimating stats at levels the wiki does not publish.
+//
+// The stats page only has tables for levels 1, 100, 120 and 125. For any other
+// level we interpolate linearly between the two nearest tables the ship has.
+// How close that gets:
+//
+// * At a table level the result is exact.
+// * Between 100 and 125 in-game growth is linear per level, so estimates are
+//   within rounding (about 1 point per stat).
+// * Between 1 and 100 growth is also linear, but the level 1 table is an
+//   unenhanced ship while the level 100 table includes full enhancement. The
+//   enhancement bonus gets spread across the range, so firepower, torpedo,
+//   aviation and reload can be overestimated by up to the enhancement amount
+//   (usually a few dozen points) at low levels.
+// * Levels outside the scraped tables are clamped, never extrapolated.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::ship::{Ship, StatBlock, ValidLevel};
+
+pub const MIN_LEVEL: i32 = 1;
+pub const MAX_LEVEL: i32 = 125;
+pub const MAX_LIMIT_BREAK: u8 = 3;
+
+// Highest level a ship can reach at a limit break stage. Past 100 a ship
+// needs to be fully limit broken.
+pub fn level_cap(limit_break: u8) -> i32 {
+    match limit_break {
+        0 => 70,
+        1 => 80,
+        2 => 90,
+        _ => MAX_LEVEL,
+    }
+}
+
+fn lerp(low: i32, high: i32, t: f64) -> i32 {
+    (low as f64 + (high - low) as f64 * t).round() as i32
+}
+
+fn interpolate(low: &StatBlock, high: &StatBlock, t: f64) -> StatBlock {
+    StatBlock::from_fn(|stat| lerp(low.get(stat), high.get(stat), t))
+}
+
+// Stats at any level from 1 to 125, or None when no level tables are known
+pub fn estimate_stats(levels: &BTreeMap<ValidLevel, StatBlock>, level: i32) -> Option<StatBlock> {
+    let level = level.clamp(MIN_LEVEL, MAX_LEVEL);
+
+    let below = levels.iter().rfind(|(l, _)| l.number() <= level);
+    let above = levels.iter().find(|(l, _)| l.number() >= level);
+
+    match (below, above) {
+        (Some((low_level, low)), Some((high_level, high))) if low_level != high_level => {
+            let t = (level - low_level.number()) as f64 / (high_level.number() - low_level.number()) as f64;
+            Some(interpolate(low, high, t))
+        }
+        (Some((_, stats)), _) | (None, Some((_, stats))) => Some(*stats),
+        (None, None) => None,
+    }
+}
+
+// A copy of the ship with its stats estimated at the given level
+pub fn ship_at_level(ship: &Ship, level: i32) -> Option<Ship> {
+    let stats = estimate_stats(&ship.levels, level)?;
+    let mut ship = ship.clone();
+    ship.set_stats(&stats);
+    Some(ship)
+}
+
+// Move every ship to the given level. Ships without multi-level data keep
+// their stats as they are; their names are returned, sorted, alongside the map.
+pub fn map_at_level(map: HashMap<i32, Ship>, level: i32) -> (HashMap<i32, Ship>, Vec<String>) {
+    let mut skipped = Vec::new();
+    let map = map
+        .into_iter()
+        .map(|(id, ship)| match ship_at_level(&ship, level) {
+            Some(at_level) => (id, at_level),
+            None => {
+                skipped.push(ship.name.clone());
+                (id, ship)
+            }
+        })
+        .collect();
+    skipped.sort();
+    (map, skipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scrape::tests::{find, fixture_ships};
+    use crate::ship::SortChoice;
+
+    fn levels() -> BTreeMap<ValidLevel, StatBlock> {
+        let block = |hp, firepower| {
+            let mut stats = StatBlock::default();
+            stats.set(SortChoice::HP, hp);
+            stats.set(SortChoice::Firepower, firepower);
+            stats
+        };
+        BTreeMap::from([
+            (ValidLevel::Level1, block(100, 10)),
+            (ValidLevel::Level100, block(1000, 100)),
+            (ValidLevel::Level120, block(1200, 121)),
+            (ValidLevel::Level125, block(1250, 130)),
+        ])
+    }
+
+    #[test]
+    fn table_levels_are_exact() {
+        let levels = levels();
+        for (level, stats) in &levels {
+            assert_eq!(estimate_stats(&levels, level.number()), Some(*stats));
+        }
+    }
+
+    #[test]
+    fn between_tables() {
+        let stats = estimate_stats(&levels(), 110).unwrap();
+        assert_eq!(stats.get(SortChoice::HP), 1100);
+        // 110.5 rounds away from zero
+        assert_eq!(stats.get(SortChoice::Firepower), 111);
+
+        let stats = estimate_stats(&levels(), 50).unwrap();
+        assert_eq!(stats.get(SortChoice::HP), 545);
+    }
+
+    #[test]
+    fn out_of_range_levels_are_clamped() {
+        let levels = levels();
+        assert_eq!(estimate_stats(&levels, 0), Some(levels[&ValidLevel::Level1]));
+        assert_eq!(estimate_stats(&levels, 126), Some(levels[&ValidLevel::Level125]));
+
+        // Only the tables the ship has are used, never extrapolated past
+        let partial = BTreeMap::from([(ValidLevel::Level100, levels[&ValidLevel::Level100])]);
+        assert_eq!(estimate_stats(&partial, 125), Some(levels[&ValidLevel::Level100]));
+        assert_eq!(estimate_stats(&BTreeMap::new(), 100), None);
+    }
+
+    #[test]
+    fn ship_stats_at_level() {
+        let map = fixture_ships();
+        let hood = find(&map, "Hood");
+
+        let at_120 = ship_at_level(hood, 120).unwrap();
+        assert_eq!(at_120.hp, 7248);
+        assert_eq!(at_120.firepower, 344);
+        assert_eq!(at_120.name, "Hood");
+
+        let at_110 = ship_at_level(hood, 110).unwrap();
+        assert_eq!(at_110.hp, 7043);
+
+        assert_eq!(ship_at_level(hood, 0).unwrap().hp, 1263);
+        assert_eq!(ship_at_level(hood, 126).unwrap().hp, 7453);
+
+        let mut single = hood.clone();
+        single.levels.clear();
+        assert_eq!(ship_at_level(&single, 120), None);
+    }
+
+    #[test]
+    fn map_reports_ships_without_tables() {
+        let mut map = fixture_ships();
+        for ship in map.values_mut().filter(|ship| ship.name == "Cassin" || ship.name == "I-19") {
+            ship.levels.clear();
+        }
+        let cassin_hp = find(&map, "Cassin").hp;
+
+        let (at_120, skipped) = map_at_level(map, 120);
+        assert_eq!(skipped, ["Cassin", "I-19"]);
+        assert_eq!(find(&at_120, "Hood").hp, 7248);
+        assert_eq!(find(&at_120, "Cassin").hp, cassin_hp);
+    }
+
+    #[test]
+    fn level_cap_by_limit_break() {
+        assert_eq!(level_cap(0), 70);
+        assert_eq!(level_cap(1), 80);
+        assert_eq!(level_cap(2), 90);
+        assert_eq!(level_cap(3), MAX_LEVEL);
+        assert_eq!(level_cap(7), MAX_LEVEL);
+    }
+}
@@ -1,5 +1,4 @@
-use reqwest;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 use std::error::Error;
 use std::path::Path;
@@ -9,14 +8,34 @@ use std::{
     str::FromStr,
 };
 
-use terminal_menu::{button, label, menu, mut_menu, run};
+use terminal_menu::{button, label, menu, mut_menu, numeric, run, scroll, string};
 
 use getopts::Options;
 
+pub mod cli;
+pub mod compare;
+pub mod fleet;
+pub mod gear;
 pub mod gui;
+pub mod images;
+pub mod level;
+pub mod query;
+pub mod radar;
+pub mod roster;
+pub mod score;
+pub mod scrape;
 pub mod ship;
-
+pub mod skill;
+pub mod tech;
+
+use crate::fleet::{allocate_fleets, build_fleet, AllocationMode, FleetConstraints};
+use crate::gear::{read_gear_from_file, write_gear_to_file, DEFAULT_GEAR_FILE};
+use crate::roster::{apply_roster, default_roster_path};
+use crate::scrape::{scrape_equipment, scrape_wiki, scrape_wiki_all_levels, Source};
+use crate::score::{sort_ships_weighted, Normalization, Normalizer, Scorer, Weights};
 use crate::ship::*;
+use crate::skill::{attach_skills, read_skills_from_file, DEFAULT_SKILLS_FILE};
+use crate::tech::{apply_fleet_tech, read_tech_from_file, DEFAULT_TECH_FILE};
 
 #[derive(Debug, Clone)]
 struct MainState {
@@ -73,23 +92,26 @@ fn read_ships_from_file<P: AsRef<Path>>(path: P) -> Result<HashMap<i32, Ship>, B
     Ok(map)
 }
 
-fn sort_ships(line: &mut Vec<Ship>, choice: SortChoice) {
-    match choice {
-        SortChoice::HP => line.sort_by(|a, b| b.hp.cmp(&a.hp)),
-        SortChoice::Luck => line.sort_by(|a, b| b.luck.cmp(&a.luck)),
-        SortChoice::Armor => line.sort_by(|a, b| b.armor.cmp(&a.armor)),
-        SortChoice::Speed => line.sort_by(|a, b| b.speed.cmp(&a.speed)),
-        SortChoice::Firepower => line.sort_by(|a, b| b.firepower.cmp(&a.firepower)),
-        SortChoice::Cost => line.sort_by(|a, b| b.cost.cmp(&a.cost)),
-        SortChoice::Reload => line.sort_by(|a, b| b.reload.cmp(&a.reload)),
-        SortChoice::Torpedo => line.sort_by(|a, b| b.torpedo.cmp(&a.torpedo)),
-        SortChoice::Evasion => line.sort_by(|a, b| b.evasion.cmp(&a.evasion)),
-        SortChoice::AntiSubmarine => line.sort_by(|a, b| b.antisubmarine.cmp(&a.antisubmarine)),
-        SortChoice::AntiAir => line.sort_by(|a, b| b.antiair.cmp(&a.antiair)),
-        SortChoice::Oxygen => line.sort_by(|a, b| b.oxygen.cmp(&a.oxygen)),
-        SortChoice::Ammunition => line.sort_by(|a, b| b.ammunition.cmp(&a.ammunition)),
-        SortChoice::Accuracy => line.sort_by(|a, b| b.accuracy.cmp(&a.accuracy)),
+// Attach skills and fleet tech to the ships. Without an explicit path the
+// default files are used when they exist.
+fn apply_account_files(map: &mut HashMap<i32, Ship>, skills: Option<&str>, tech: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let skills = skills.or_else(|| Path::new(DEFAULT_SKILLS_FILE).exists().then_some(DEFAULT_SKILLS_FILE));
+    if let Some(path) = skills {
+        let skills = read_skills_from_file(path).map_err(|e| format!("could not read {}: {}", path, e))?;
+        attach_skills(map, &skills);
+    }
+
+    let tech = tech.or_else(|| Path::new(DEFAULT_TECH_FILE).exists().then_some(DEFAULT_TECH_FILE));
+    if let Some(path) = tech {
+        let tech = read_tech_from_file(path).map_err(|e| format!("could not read {}: {}", path, e))?;
+        apply_fleet_tech(map, &tech);
     }
+    Ok(())
+}
+
+// Sort by the first key, falling back to the following keys on ties
+fn sort_ships(line: &mut [Ship], keys: &[SortKey]) {
+    line.sort_by(|a, b| compare_ships(a, b, keys));
 }
 
 fn find_line(map: &HashMap<i32, Ship>) -> (Vec<Ship>, Vec<Ship>, Vec<Ship>) {
@@ -114,93 +136,22 @@ fn find_line(map: &HashMap<i32, Ship>) -> (Vec<Ship>, Vec<Ship>, Vec<Ship>) {
     (backline, frontline, subline)
 }
 
-fn filter_line_class(line: &Vec<Ship>, filter: Class) -> Vec<&Ship> { line.iter().filter(|x| x.class == filter).collect() }
-
-fn filter_line_armor(line: &Vec<Ship>, filter: Armor) -> Vec<&Ship> { line.iter().filter(|x| x.armor == filter).collect() }
-
-fn scrape_wiki(level: i32) -> Result<HashMap<i32, Ship>, Box<dyn Error>> {
-    let azur_wiki_url = "https://azurlane.koumakan.jp/wiki/List_of_Ships_by_Stats";
-    let response = reqwest::blocking::get(azur_wiki_url)?.text()?;
-
-
-    let mut map = HashMap::new();
-
-    let wiki = response; // full response from wiki
-    let mut count = 0;
-
-    let document = scraper::Html::parse_document(&wiki);
-
-    // Select which table we want.. right now we get Level 100 because of 2nd child
-    // 1 > 100 > 120 > 125
+fn filter_line_class(line: &[Ship], filter: Class) -> Vec<&Ship> { line.iter().filter(|x| x.class.family() == filter.family()).collect() }
 
-    let string = match level {
-        1 => "article:nth-child(1) > table > tbody ",
-        100 => "article:nth-child(2) > table > tbody ",
-        120 => "article:nth-child(3) > table > tbody ",
-        125 => "article:nth-child(4) > table > tbody ",
-        _ => "article:nth-child(1) > table > tbody ",
-    };
-    let article = scraper::Selector::parse(string).unwrap();
-
-    for rows in document.select(&article) {
-        let mut row = rows.text().collect::<Vec<_>>();
-        // Remove heading
-        row.remove(0); // ID
-        row.remove(0); // Ship Name
-        row.remove(0); // Rarity
-        row.remove(0); // Nation
-        row.remove(0); // Type
+fn filter_line_armor(line: &[Ship], filter: Armor) -> Vec<&Ship> { line.iter().filter(|x| x.armor == filter).collect() }
 
-        let len = row.len(); // number of ships per category
-                             //println!("{:?}", len);
-
-        let mut index = 0;
-        loop {
-            // NOTE: all icons aren't in the header but the data is here
-            println!("{:?}", &row[index..index + 19]);
-
-            // build image url for ship
-            let mut image_url = String::from("https://azurlane.koumakan.jp/wiki/File:");
-            image_url.push_str(
-                &row[index + 1]
-                    .replace(" (Retrofit)", "Kai")
-                    .replace(" ", "_"),
-            ); // name of ship
-            image_url.push_str("Icon.png");
-
-            let ship = Ship {
-                id: String::from(row[index + 0]),
-                name: String::from(row[index + 1]),
-                rarity: String::from(row[index + 2]),
-                nation: String::from(row[index + 3]),
-                class: Class::from_str(row[index + 4]).unwrap(),
-                luck: row[index + 5].parse().unwrap_or(0),
-                armor: Armor::from_str(row[index + 6]).unwrap(),
-                speed: row[index + 7].parse().unwrap_or(0),
-                hp: row[index + 8].parse().unwrap_or(0),
-                firepower: row[index + 9].parse().unwrap_or(0),
-                antiair: row[index + 10].parse().unwrap_or(0),
-                torpedo: row[index + 11].parse().unwrap_or(0),
-                evasion: row[index + 12].parse().unwrap_or(0),
-                aviation: row[index + 13].parse().unwrap_or(0),
-                cost: row[index + 14].parse().unwrap_or(0),
-                reload: row[index + 15].parse().unwrap_or(0),
-                antisubmarine: row[index + 16].parse().unwrap_or(0),
-                oxygen: row[index + 17].parse().unwrap_or(0),
-                ammunition: row[index + 18].parse().unwrap_or(0),
-                accuracy: row[index + 19].parse().unwrap_or(0),
-                image: image_url,
-            };
-            //println!("{:?}", ship);
-            map.insert(count, ship);
-            count += 1;
-            index += 20; // 20 fields per row in the table
-            if index >= len {
-                break;
-            };
-        }
+// Ships grouped by a field such as nation or rarity, groups in enum order
+fn group_ships<'a, K: Ord>(ships: impl IntoIterator<Item = &'a Ship>, key: fn(&Ship) -> K) -> BTreeMap<K, Vec<&'a Ship>> {
+    let mut groups: BTreeMap<K, Vec<&Ship>> = BTreeMap::new();
+    for ship in ships {
+        groups.entry(key(ship)).or_default().push(ship);
     }
-    Ok(map)
+    groups
+}
+
+// Groups keyed by their display name, keeping the enum order
+fn labelled<K: std::fmt::Display>(groups: BTreeMap<K, Vec<&Ship>>) -> Vec<(String, Vec<&Ship>)> {
+    groups.into_iter().map(|(key, ships)| (key.to_string(), ships)).collect()
 }
 
 #[allow(dead_code)]
@@ -212,22 +163,83 @@ fn export_json<P: AsRef<Path>>(path: P, all_lines: &mut Vec<Ship>) -> std::io::R
     Ok(())
 }
 
+// Ask for the fleet objective and constraints
+fn read_fleet_options(map: &HashMap<i32, Ship>) -> Result<(Scorer, FleetConstraints), Box<dyn Error>> {
+    let fleet_menu = menu(vec![
+        label("Objective weights as stat=weight, comma separated"),
+        string("Weights", "firepower=1.0, aviation=1.0, torpedo=0.8", false),
+        scroll(
+            "Normalization",
+            Normalization::ALL.iter().map(|n| n.to_string()).collect::<Vec<_>>(),
+        ),
+        label("Constraints (0 or empty for none)"),
+        numeric("Max cost", 0.0, Some(1.0), Some(0.0), None),
+        string("Min classes", "CV", true),
+        string("Must include", "", true),
+        numeric("Subs", 3.0, Some(1.0), Some(0.0), Some(3.0)),
+        button("Build"),
+    ]);
+
+    run(&fleet_menu);
+
+    let fleet_menu = mut_menu(&fleet_menu);
+    let weights = Weights::from_str(fleet_menu.selection_value("Weights"))?;
+    let mode = Normalization::from_str(fleet_menu.selection_value("Normalization")).unwrap_or_default();
+    let max_cost = fleet_menu.numeric_value("Max cost") as i32;
+
+    let constraints = FleetConstraints {
+        min_classes: FleetConstraints::parse_min_classes(fleet_menu.selection_value("Min classes"))?,
+        max_cost: if max_cost > 0 { Some(max_cost) } else { None },
+        required: fleet_menu
+            .selection_value("Must include")
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(String::from)
+            .collect(),
+        max_subs: fleet_menu.numeric_value("Subs") as usize,
+        ..FleetConstraints::default()
+    };
+
+    Ok((Scorer::new(weights, Normalizer::new(map.values(), mode)), constraints))
+}
+
 fn print_usage(program: &str, opts: Options) {
-    let brief = format!("Usage: {} [options]", program);
+    let brief = format!(
+        "Usage: {} [options]\n       {} <{}> [options]",
+        program,
+        program,
+        cli::SUBCOMMANDS.join("|")
+    );
     print!("{}", opts.usage(&brief));
 }
 
 fn main() -> iced::Result {
     let args: Vec<String> = env::args().collect();
     let program = args[0].clone();
+
+    if args.len() > 1 && cli::is_subcommand(&args[1]) {
+        std::process::exit(cli::run(&program, &args[1], &args[2..]));
+    }
+
     let mut opts = Options::new();
     opts.optflag("g", "gui", "use GUI");
     opts.optflag("h", "help", "help menu");
 
     let matches = match opts.parse(&args[1..]) {
         Ok(m) => m,
-        Err(_) => todo!(),
+        Err(e) => {
+            eprintln!("{}: {}", program, e);
+            print_usage(&program, opts);
+            std::process::exit(2);
+        }
     };
+    // Anything left over is a mistyped subcommand, not a reason to open the menu
+    if let Some(arg) = matches.free.first() {
+        eprintln!("{}: unknown subcommand \"{}\"", program, arg);
+        print_usage(&program, opts);
+        std::process::exit(2);
+    }
 
     if matches.opt_present("h") {
         print_usage(&program, opts);
@@ -248,9 +260,15 @@ fn main() -> iced::Result {
                 label("1) Read Ships into program"),
                 label("2) Sort Ships"),
                 label("3) Scrape wiki"),
+                label("4) Build fleet"),
+                label("5) Allocate several fleets"),
+                label("6) Group ships by nation or rarity"),
                 button("1"),
                 button("2"),
                 button("3"),
+                button("4"),
+                button("5"),
+                button("6"),
 
                 button("Quit"),
             ]);
@@ -259,65 +277,152 @@ fn main() -> iced::Result {
 
             match mut_menu(&main_menu).selected_item_name() {
                 "1" => {
-                    let map3 = read_ships_from_file("data_export.json").unwrap();
-                    state.set_map(map3); 
+                    let import_menu = menu(vec![
+                        label("Import which ships?"),
+                        button("All"),
+                        button("Roster"),
+                    ]);
+
+                    run(&import_menu);
+
+                    let mut map3 = read_ships_from_file("data_export.json").unwrap();
+                    if let Err(e) = apply_account_files(&mut map3, None, None) {
+                        eprintln!("{}", e);
+                    }
+                    if mut_menu(&import_menu).selected_item_name() == "Roster" {
+                        let gear = read_gear_from_file(DEFAULT_GEAR_FILE).unwrap_or_default();
+                        // Keep every ship rather than lose the import to a bad roster
+                        match apply_roster(default_roster_path(), map3.clone(), &gear) {
+                            Ok(filtered) => map3 = filtered,
+                            Err(e) => eprintln!("could not read {}: {}", default_roster_path(), e),
+                        }
+                    }
+                    state.set_map(map3);
                 }
                 "2" => {
                     let (backline, frontline, subline) = find_line(state.get_map());
 
                     for mut line in [backline, frontline, subline] {
-                        let selection_menu = menu(vec![
-                            label("Select Stat to Sort by:"),
-                            button("Luck"),
-                            button("Armor"),
-                            button("Speed"),
-                            button("HP"),
-                            button("Firepower"),
-                            button("AntiAir"),
-                            button("Torpedo"),
-                            button("Evasion"),
-                            button("Cost"),
-                            button("Reload"),
-                            button("AntiSubmarine"),
-                            button("Oxygen"),
-                            button("Ammunition"),
-                            button("Accuracy"),
-                        ]);
+                        let mut items = vec![label("Select Stat to Sort by:")];
+                        items.extend(SortChoice::all().iter().map(|choice| button(choice.name())));
+                        items.push(button("Weighted"));
+                        let selection_menu = menu(items);
 
                         run(&selection_menu);
 
-                        sort_ships(
-                            &mut line,
-                            SortChoice::from_str(mut_menu(&selection_menu).selected_item_name())
-                                .unwrap(),
-                        );
-
-                        println!("{}", line[0]);
-                        println!("{}", line[1]);
-                        println!("{}", line[2]);
+                        let choice = mut_menu(&selection_menu).selected_item_name().to_string();
+                        if choice == "Weighted" {
+                            let weights_menu = menu(vec![
+                                label("Weights as stat=weight, comma separated"),
+                                string("Weights", "torpedo=1.0, reload=0.6, evasion=0.4", false),
+                                scroll(
+                                    "Normalization",
+                                    Normalization::ALL.iter().map(|n| n.to_string()).collect::<Vec<_>>(),
+                                ),
+                                button("Sort"),
+                            ]);
+
+                            run(&weights_menu);
+
+                            let weights = match Weights::from_str(mut_menu(&weights_menu).selection_value("Weights")) {
+                                Ok(w) => w,
+                                Err(e) => {
+                                    eprintln!("{}", e);
+                                    continue;
+                                }
+                            };
+                            let mode = Normalization::from_str(mut_menu(&weights_menu).selection_value("Normalization"))
+                                .unwrap_or_default();
+                            let scorer = Scorer::new(weights, Normalizer::new(state.get_map().values(), mode));
+
+                            sort_ships_weighted(&mut line, &scorer);
+
+                            for ship in line.iter().take(3) {
+                                println!("{} ({:.2})", ship, scorer.score(ship));
+                            }
+                        } else {
+                            let stat = SortChoice::from_str(&choice).unwrap();
+                            let default_direction = stat.default_direction();
+                            let order_menu = menu(vec![
+                                label(format!("Sort by {}", stat)),
+                                scroll(
+                                    "Direction",
+                                    SortDirection::ALL
+                                        .iter()
+                                        .filter(|d| **d == default_direction)
+                                        .chain(SortDirection::ALL.iter().filter(|d| **d != default_direction))
+                                        .map(|d| d.to_string())
+                                        .collect::<Vec<_>>(),
+                                ),
+                                string("Then by", "cost:asc", true),
+                                label("Ties are finally broken by name"),
+                                button("Sort"),
+                            ]);
+
+                            run(&order_menu);
+
+                            let direction = SortDirection::from_str(mut_menu(&order_menu).selection_value("Direction"))
+                                .unwrap_or(default_direction);
+                            let mut keys = vec![SortKey::new(stat, direction)];
+                            match SortKey::parse_chain(mut_menu(&order_menu).selection_value("Then by")) {
+                                Ok(then_by) => keys.extend(then_by),
+                                Err(e) => {
+                                    eprintln!("{}", e);
+                                    continue;
+                                }
+                            }
+
+                            sort_ships(&mut line, &keys);
+
+                            for ship in line.iter().take(3) {
+                                println!("{}", ship);
+                            }
+                        }
                     }
                 }
                 "3" => {
                     let wiki_menu = menu(vec![
+                        label("Saved page or directory to scrape offline (empty for the live wiki)"),
+                        string("Snapshot", "", true),
                         button("Level1"),
                         button("Level100"),
                         button("Level120"),
                         button("Level125"),
+                        button("AllLevels"),
+                        button("Equipment"),
                     ]);
 
                     run(&wiki_menu);
 
-                    let level =
-                        match ValidLevel::from_str(mut_menu(&wiki_menu).selected_item_name())
-                            .unwrap()
-                        {
-                            ValidLevel::Level1 => 1,
-                            ValidLevel::Level100 => 100,
-                            ValidLevel::Level120 => 120,
-                            ValidLevel::Level125 => 125,
-                        };
-
-                    let map2 = scrape_wiki(level).unwrap();
+                    let source = Source::from_path(mut_menu(&wiki_menu).selection_value("Snapshot"));
+                    let choice = mut_menu(&wiki_menu).selected_item_name().to_string();
+                    if choice == "Equipment" {
+                        match scrape_equipment(&source) {
+                            Ok((gear, report)) => {
+                                println!("{}", report);
+                                if let Err(e) = write_gear_to_file(DEFAULT_GEAR_FILE, &gear) {
+                                    eprintln!("could not write {}: {}", DEFAULT_GEAR_FILE, e);
+                                }
+                            }
+                            Err(e) => eprintln!("{}", e),
+                        }
+                        continue;
+                    }
+                    let scraped = if choice == "AllLevels" {
+                        // Keep level 120 in the flat stats, the rest under each ship's levels
+                        scrape_wiki_all_levels(&source, ValidLevel::Level120)
+                    } else {
+                        scrape_wiki(&source, ValidLevel::from_str(&choice).unwrap().number())
+                    };
+
+                    let (map2, report) = match scraped {
+                        Ok(scraped) => scraped,
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            continue;
+                        }
+                    };
+                    println!("{}", report);
                     state.set_map(map2);
                     let (mut backline, mut frontline, mut subline) = find_line(state.get_map());
                     let all_lines = &mut backline;
@@ -326,6 +431,88 @@ fn main() -> iced::Result {
                     let _ = export_json("data_export.json", all_lines);
 
                 }
+                "4" => {
+                    let (scorer, constraints) = match read_fleet_options(state.get_map()) {
+                        Ok(options) => options,
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            continue;
+                        }
+                    };
+
+                    match build_fleet(state.get_map(), &scorer, &constraints) {
+                        Ok(fleet) => println!("{}", fleet),
+                        Err(e) => eprintln!("{}", e),
+                    }
+                }
+                "5" => {
+                    let (scorer, constraints) = match read_fleet_options(state.get_map()) {
+                        Ok(options) => options,
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            continue;
+                        }
+                    };
+
+                    let allocation_menu = menu(vec![
+                        numeric("Fleets", 2.0, Some(1.0), Some(1.0), None),
+                        scroll(
+                            "Optimize",
+                            AllocationMode::ALL.iter().map(|m| m.to_string()).collect::<Vec<_>>(),
+                        ),
+                        button("Allocate"),
+                    ]);
+
+                    run(&allocation_menu);
+
+                    let count = mut_menu(&allocation_menu).numeric_value("Fleets") as usize;
+                    let mode = AllocationMode::from_str(mut_menu(&allocation_menu).selection_value("Optimize"))
+                        .unwrap_or_default();
+
+                    match allocate_fleets(state.get_map(), count, &scorer, &constraints, mode) {
+                        Ok(fleets) => {
+                            for (index, fleet) in fleets.iter().enumerate() {
+                                println!("Fleet {}", index + 1);
+                                println!("{}", fleet);
+                            }
+                        }
+                        Err(e) => eprintln!("{}", e),
+                    }
+                }
+                "6" => {
+                    let group_menu = menu(vec![
+                        label("Group the imported ships"),
+                        scroll("Group by", vec!["Nation", "Rarity"]),
+                        label("Only show one nation or rarity (empty for all)"),
+                        string("Only", "", true),
+                        button("Show"),
+                    ]);
+
+                    run(&group_menu);
+
+                    let by_nation = mut_menu(&group_menu).selection_value("Group by") == "Nation";
+                    let only = mut_menu(&group_menu).selection_value("Only").trim().to_string();
+                    let ships = state.get_map().values();
+                    let groups: Vec<(String, Vec<&Ship>)> = if by_nation {
+                        group_ships(ships, |s| s.nation.clone())
+                            .into_iter()
+                            .filter(|(nation, _)| only.is_empty() || *nation == Nation::from(only.as_str()))
+                            .map(|(nation, ships)| (nation.to_string(), ships))
+                            .collect()
+                    } else {
+                        group_ships(ships, |s| s.rarity.clone())
+                            .into_iter()
+                            .filter(|(rarity, _)| only.is_empty() || *rarity == Rarity::from(only.as_str()))
+                            .map(|(rarity, ships)| (rarity.to_string(), ships))
+                            .collect()
+                    };
+
+                    for (group, mut members) in groups {
+                        members.sort_by(|a, b| a.name.cmp(&b.name));
+                        let names: Vec<&str> = members.iter().map(|s| s.name.as_str()).collect();
+                        println!("{} ({}): {}", group, members.len(), names.join(", "));
+                    }
+                }
                 "Quit" => {
                     break;
                 }
@@ -0,0 +1,505 @@
+// A small filter language for picking ships, e.g.
+//
+//   class in (CV, CVL) and nation = "Sakura Empire" and aviation > 350 and not name ~ "META"
+//
+// Comparisons are `=`, `!=`, `<`, `<=`, `>`, `>=`, `~` (contains) and
+// `in (..)`, combined with `and`, `or`, `not` and parentheses. Text matches
+// ignore case. Stats compare by their effective value, the same number
+// sorting uses.
+
+use core::fmt;
+use std::error::Error;
+use std::str::FromStr;
+
+use crate::ship::{Ship, SortChoice};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Name,
+    Id,
+    Class,
+    Armor,
+    Nation,
+    Rarity,
+    Affinity,
+    Stat(SortChoice),
+}
+
+impl Field {
+    fn parse(word: &str) -> Option<Field> {
+        let field = match word.to_lowercase().as_str() {
+            "name" => Field::Name,
+            "id" => Field::Id,
+            "class" | "type" | "hull" => Field::Class,
+            "armor" => Field::Armor,
+            "nation" | "faction" => Field::Nation,
+            "rarity" => Field::Rarity,
+            "affinity" => Field::Affinity,
+            _ => Field::Stat(SortChoice::from_str(word).ok()?),
+        };
+        Some(field)
+    }
+
+    fn is_numeric(&self) -> bool {
+        matches!(self, Field::Stat(_))
+    }
+
+    // Text forms of the field a value can match. Classes match both their
+    // data name and the one shown in lists ("BC" and "BBC").
+    fn text(&self, ship: &Ship) -> Vec<String> {
+        match self {
+            Field::Name => vec![ship.name.clone()],
+            Field::Id => vec![ship.id.clone()],
+            Field::Class => vec![format!("{:?}", ship.class), ship.class.to_string()],
+            Field::Armor => vec![format!("{:?}", ship.armor)],
+            Field::Nation => vec![ship.nation.to_string()],
+            Field::Rarity => vec![ship.rarity.to_string()],
+            Field::Affinity => {
+                let affinity = ship.affinity.unwrap_or_default();
+                vec![format!("{:?}", affinity), affinity.to_string()]
+            }
+            Field::Stat(stat) => vec![stat.value(ship).to_string()],
+        }
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Field::Stat(stat) => write!(f, "{}", stat),
+            _ => write!(f, "{:?}", self),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let symbol = match self {
+            Op::Eq => "=",
+            Op::Ne => "!=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+            Op::Contains => "~",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(f64),
+    Text(String),
+}
+
+impl Value {
+    fn as_text(&self) -> String {
+        match self {
+            Value::Number(n) => n.to_string(),
+            Value::Text(t) => t.clone(),
+        }
+    }
+}
+
+// The parsed form of a query
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Field, Op, Value),
+    In(Field, Vec<Value>),
+}
+
+impl Expr {
+    pub fn matches(&self, ship: &Ship) -> bool {
+        match self {
+            Expr::And(a, b) => a.matches(ship) && b.matches(ship),
+            Expr::Or(a, b) => a.matches(ship) || b.matches(ship),
+            Expr::Not(e) => !e.matches(ship),
+            Expr::Compare(field, op, value) => compare(ship, *field, *op, value),
+            Expr::In(field, values) => values.iter().any(|v| compare(ship, *field, Op::Eq, v)),
+        }
+    }
+}
+
+fn compare(ship: &Ship, field: Field, op: Op, value: &Value) -> bool {
+    if let (Field::Stat(stat), Value::Number(n)) = (field, value) {
+        let actual = stat.value(ship) as f64;
+        return match op {
+            Op::Eq => actual == *n,
+            Op::Ne => actual != *n,
+            Op::Lt => actual < *n,
+            Op::Le => actual <= *n,
+            Op::Gt => actual > *n,
+            Op::Ge => actual >= *n,
+            Op::Contains => actual.to_string().contains(&n.to_string()),
+        };
+    }
+
+    let wanted = value.as_text().to_lowercase();
+    let texts: Vec<String> = field.text(ship).iter().map(|t| t.to_lowercase()).collect();
+    match op {
+        Op::Eq => texts.contains(&wanted),
+        Op::Ne => !texts.contains(&wanted),
+        Op::Contains => texts.iter().any(|t| t.contains(&wanted)),
+        // rejected by the parser
+        Op::Lt | Op::Le | Op::Gt | Op::Ge => false,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryError {
+    pub position: usize, // character offset into the query
+    pub message: String,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "at column {}: {}", self.position + 1, self.message)
+    }
+}
+
+impl Error for QueryError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Text(String),
+    Number(f64),
+    Op(Op),
+    Open,
+    Close,
+    Comma,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Word(w) => write!(f, "\"{}\"", w),
+            Token::Text(t) => write!(f, "\"{}\"", t),
+            Token::Number(n) => write!(f, "{}", n),
+            Token::Op(op) => write!(f, "\"{}\"", op),
+            Token::Open => write!(f, "\"(\""),
+            Token::Close => write!(f, "\")\""),
+            Token::Comma => write!(f, "\",\""),
+        }
+    }
+}
+
+fn error<T>(position: usize, message: impl Into<String>) -> Result<T, QueryError> {
+    Err(QueryError { position, message: message.into() })
+}
+
+fn tokenize(input: &str) -> Result<Vec<(usize, Token)>, QueryError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        let two = |next: char| chars.get(i + 1) == Some(&next);
+
+        let token = match c {
+            _ if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '(' => Token::Open,
+            ')' => Token::Close,
+            ',' => Token::Comma,
+            '~' => Token::Op(Op::Contains),
+            '=' if two('=') => {
+                i += 1;
+                Token::Op(Op::Eq)
+            }
+            '=' => Token::Op(Op::Eq),
+            '!' if two('=') => {
+                i += 1;
+                Token::Op(Op::Ne)
+            }
+            '<' if two('=') => {
+                i += 1;
+                Token::Op(Op::Le)
+            }
+            '<' => Token::Op(Op::Lt),
+            '>' if two('=') => {
+                i += 1;
+                Token::Op(Op::Ge)
+            }
+            '>' => Token::Op(Op::Gt),
+            '"' | '\'' => {
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|&q| q == c)
+                    .ok_or(QueryError { position: start, message: String::from("unclosed quote") })?;
+                let text: String = chars[i + 1..i + 1 + end].iter().collect();
+                i += end + 1;
+                Token::Text(text)
+            }
+            _ if c.is_ascii_digit() || (c == '-' && chars.get(i + 1).is_some_and(|d| d.is_ascii_digit())) => {
+                let len = chars[i + 1..].iter().take_while(|d| d.is_ascii_digit() || **d == '.').count();
+                let number: String = chars[i..=i + len].iter().collect();
+                i += len;
+                match number.parse() {
+                    Ok(n) => Token::Number(n),
+                    Err(_) => return error(start, format!("bad number \"{}\"", number)),
+                }
+            }
+            _ if c.is_alphanumeric() || c == '_' => {
+                let len = chars[i..]
+                    .iter()
+                    .take_while(|w| w.is_alphanumeric() || matches!(w, '_' | '-' | '.'))
+                    .count();
+                let word: String = chars[i..i + len].iter().collect();
+                i += len - 1;
+                Token::Word(word)
+            }
+            _ => return error(start, format!("unexpected \"{}\"", c)),
+        };
+        tokens.push((start, token));
+        i += 1;
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    next: usize,
+    end: usize, // position reported for errors at the end of the input
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next).map(|(_, t)| t)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens.get(self.next).map_or(self.end, |(p, _)| *p)
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.next).map(|(_, t)| t.clone());
+        self.next += 1;
+        token
+    }
+
+    fn keyword(&mut self, word: &str) -> bool {
+        match self.peek() {
+            Some(Token::Word(w)) if w.eq_ignore_ascii_case(word) => {
+                self.next += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), QueryError> {
+        let position = self.position();
+        match self.advance() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => error(position, format!("expected {}, found {}", expected, token)),
+            None => error(position, format!("expected {}", expected)),
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, QueryError> {
+        let mut expr = self.and()?;
+        while self.keyword("or") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, QueryError> {
+        let mut expr = self.unary()?;
+        while self.keyword("and") {
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, QueryError> {
+        if self.keyword("not") {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        if self.peek() == Some(&Token::Open) {
+            self.next += 1;
+            let expr = self.or()?;
+            self.expect(Token::Close)?;
+            return Ok(expr);
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, QueryError> {
+        let position = self.position();
+        let field = match self.advance() {
+            Some(Token::Word(word)) => match Field::parse(&word) {
+                Some(field) => field,
+                None => return error(position, format!("unknown field \"{}\"", word)),
+            },
+            Some(token) => return error(position, format!("expected a field name, found {}", token)),
+            None => return error(position, "expected a field name"),
+        };
+
+        if self.keyword("in") {
+            self.expect(Token::Open)?;
+            let mut values = vec![self.value(field)?];
+            while self.peek() == Some(&Token::Comma) {
+                self.next += 1;
+                values.push(self.value(field)?);
+            }
+            self.expect(Token::Close)?;
+            return Ok(Expr::In(field, values));
+        }
+
+        let position = self.position();
+        let op = match self.advance() {
+            Some(Token::Op(op)) => op,
+            _ => return error(position, format!("expected a comparison after {}", field)),
+        };
+        if !field.is_numeric() && matches!(op, Op::Lt | Op::Le | Op::Gt | Op::Ge) {
+            return error(position, format!("{} is text and can't be compared with {}", field, op));
+        }
+        Ok(Expr::Compare(field, op, self.value(field)?))
+    }
+
+    fn value(&mut self, field: Field) -> Result<Value, QueryError> {
+        let position = self.position();
+        let value = match self.advance() {
+            Some(Token::Number(n)) => Value::Number(n),
+            Some(Token::Text(t)) | Some(Token::Word(t)) => Value::Text(t),
+            Some(token) => return error(position, format!("expected a value, found {}", token)),
+            None => return error(position, "expected a value"),
+        };
+        if field.is_numeric() && !matches!(value, Value::Number(_)) {
+            return error(position, format!("{} needs a number", field));
+        }
+        Ok(value)
+    }
+}
+
+impl FromStr for Expr {
+    type Err = QueryError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(input)?,
+            next: 0,
+            end: input.chars().count(),
+        };
+        let expr = parser.or()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(token) => error(parser.position(), format!("unexpected {}", token)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scrape::tests::fixture_ships;
+
+    // Names of the fixture ships the query picks, sorted
+    fn names(query: &str) -> Vec<String> {
+        let expr: Expr = query.parse().unwrap();
+        let mut names: Vec<String> = fixture_ships().values().filter(|s| expr.matches(s)).map(|s| s.name.clone()).collect();
+        names.sort();
+        names
+    }
+
+    fn parse_error(query: &str) -> QueryError {
+        query.parse::<Expr>().unwrap_err()
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(names("class = DD or class = BC and nation = \"Eagle Union\""), ["Cassin", "Javelin (Retrofit)"]);
+        assert_eq!(
+            "class = DD or class = BC and hp > 1".parse::<Expr>().unwrap(),
+            Expr::Or(
+                Box::new(Expr::Compare(Field::Class, Op::Eq, Value::Text("DD".into()))),
+                Box::new(Expr::And(
+                    Box::new(Expr::Compare(Field::Class, Op::Eq, Value::Text("BC".into()))),
+                    Box::new(Expr::Compare(Field::Stat(SortChoice::HP), Op::Gt, Value::Number(1.0))),
+                )),
+            )
+        );
+    }
+
+    #[test]
+    fn not_binds_tightest() {
+        assert_eq!(names("not class = DD and hp > 1000"), ["Enterprise", "Hood", "I-19", "Prinz Eugen"]);
+        assert_eq!(names("not (class = DD or hp > 1500)"), ["I-19"]);
+        assert_eq!(names("not not name = Hood"), ["Hood"]);
+    }
+
+    #[test]
+    fn brackets_group() {
+        assert_eq!(names("(class = DD or class = BC) and nation = \"Royal Navy\""), ["Hood", "Javelin (Retrofit)"]);
+        assert_eq!(names("class = DD or (class = BC and nation = \"Royal Navy\")"), ["Cassin", "Hood", "Javelin (Retrofit)"]);
+        assert_eq!(parse_error("(class = DD").to_string(), "at column 12: expected \")\"");
+    }
+
+    #[test]
+    fn in_lists() {
+        assert_eq!(names("class in (DD, SS)"), ["Cassin", "I-19", "Javelin (Retrofit)"]);
+        // Classes match their list name too
+        assert_eq!(names("hull in (BBC)"), ["Hood"]);
+        assert_eq!(names("hp in (1446, 6838)"), ["Cassin", "Hood"]);
+        assert_eq!(parse_error("class in DD").position, 9);
+    }
+
+    #[test]
+    fn quoted_strings() {
+        assert_eq!(names("nation = 'Sakura Empire'"), ["I-19"]);
+        assert_eq!(names("name ~ \"(retrofit)\""), ["Javelin (Retrofit)"]);
+        assert_eq!(names("name = \"hood\""), ["Hood"]);
+        assert_eq!(parse_error("name = \"Hood").to_string(), "at column 8: unclosed quote");
+    }
+
+    #[test]
+    fn numbers_compare_as_numbers() {
+        // As text "10" and "15" would sort before "7"
+        assert_eq!(names("cost < 10"), ["Cassin", "I-19", "Javelin (Retrofit)"]);
+        assert_eq!(names("cost >= 10 and cost != 15"), ["Enterprise", "Prinz Eugen"]);
+        // Text fields compare numbers as text
+        assert_eq!(names("id = 5"), ["Cassin"]);
+
+        assert_eq!(parse_error("hp > high").message, "HP needs a number");
+        assert_eq!(parse_error("name < Hood").message, "Name is text and can't be compared with <");
+    }
+
+    #[test]
+    fn unknown_fields() {
+        let error = parse_error("speedy > 3");
+        assert_eq!(error.message, "unknown field \"speedy\"");
+        assert_eq!(error.position, 0);
+        assert_eq!(parse_error("hp > 1 and hull_type = DD").position, 11);
+    }
+
+    #[test]
+    fn error_columns() {
+        let error = parse_error("hp > 10 and class < DD");
+        assert_eq!(error.position, 18);
+        assert_eq!(error.to_string(), "at column 19: Class is text and can't be compared with <");
+        assert_eq!(parse_error("hp > 10 )").to_string(), "at column 9: unexpected \")\"");
+        assert_eq!(parse_error("hp >").to_string(), "at column 5: expected a value");
+        assert_eq!(parse_error("hp # 3").to_string(), "at column 4: unexpected \"#\"");
+    }
+}
@@ -0,0 +1,96 @@
+use std::f32::consts::PI;
+
+use iced::alignment;
+use iced::mouse;
+use iced::widget::canvas::{self, Frame, Geometry, Path, Stroke, Text};
+use iced::{Color, Point, Rectangle, Renderer, Theme, Vector};
+
+// Grid rings drawn at these fractions of the full radius
+const RINGS: [f32; 4] = [0.25, 0.5, 0.75, 1.0];
+
+// Room left around the chart for the axis labels
+const LABEL_MARGIN: f32 = 40.0;
+
+// A spider chart of values between 0 and 1, one spoke per axis starting at
+// the top and going clockwise. Drawn with iced's canvas, so it also works on
+// the software renderer.
+#[derive(Debug, Clone, Default)]
+pub struct RadarChart {
+    pub axes: Vec<(String, f32)>,
+}
+
+impl RadarChart {
+    pub fn new(axes: Vec<(String, f32)>) -> Self {
+        RadarChart { axes }
+    }
+
+    fn point(&self, center: Point, radius: f32, axis: usize, value: f32) -> Point {
+        let angle = 2.0 * PI * axis as f32 / self.axes.len() as f32 - PI / 2.0;
+        center + Vector::new(angle.cos(), angle.sin()) * (radius * value.clamp(0.0, 1.0))
+    }
+
+    fn polygon(&self, center: Point, radius: f32, values: impl Iterator<Item = f32>) -> Path {
+        Path::new(|builder| {
+            for (axis, value) in values.enumerate() {
+                let point = self.point(center, radius, axis, value);
+                if axis == 0 {
+                    builder.move_to(point);
+                } else {
+                    builder.line_to(point);
+                }
+            }
+            builder.close();
+        })
+    }
+}
+
+impl<Message> canvas::Program<Message> for RadarChart {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &(),
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        // A polygon needs at least three corners
+        if self.axes.len() < 3 {
+            return vec![frame.into_geometry()];
+        }
+
+        let palette = theme.extended_palette();
+        let center = frame.center();
+        let radius = (bounds.width.min(bounds.height) / 2.0 - LABEL_MARGIN).max(10.0);
+        let grid = Stroke::default().with_width(1.0).with_color(palette.background.strong.color);
+
+        for ring in RINGS {
+            frame.stroke(&self.polygon(center, radius, self.axes.iter().map(|_| ring)), grid.clone());
+        }
+        for axis in 0..self.axes.len() {
+            frame.stroke(&Path::line(center, self.point(center, radius, axis, 1.0)), grid.clone());
+        }
+
+        let shape = self.polygon(center, radius, self.axes.iter().map(|(_, value)| *value));
+        let accent = palette.primary.base.color;
+        frame.fill(&shape, Color { a: 0.35, ..accent });
+        frame.stroke(&shape, Stroke::default().with_width(2.0).with_color(accent));
+
+        for (axis, (label, _)) in self.axes.iter().enumerate() {
+            let position = self.point(center, radius + LABEL_MARGIN / 2.0, axis, 1.0);
+            frame.fill_text(Text {
+                content: label.clone(),
+                position,
+                color: palette.background.base.text,
+                size: 12.0.into(),
+                horizontal_alignment: alignment::Horizontal::Center,
+                vertical_alignment: alignment::Vertical::Center,
+                ..Text::default()
+            });
+        }
+
+        vec![frame.into_geometry()]
+    }
+}
@@ -0,0 +1,343 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::gear::{equip, Gear};
+use crate::level::{level_cap, ship_at_level, MAX_LEVEL, MAX_LIMIT_BREAK, MIN_LEVEL};
+use crate::ship::{Affinity, Ship};
+
+// One owned ship from the roster. A plain text roster has one name per line,
+// optionally with its level: "Javelin @ 118". A JSON roster is a list of
+// entries that can also record account progress:
+//
+// [{"name": "Javelin", "level": 120, "limit_break": 3, "affinity": "Oath",
+//   "retrofit": true, "enhanced": true,
+//   "gear": ["Quadruple 533mm Torpedo", null, "Twin 40mm Bofors", null, null]}]
+//
+// Affinity is one of Disappointed, Stranger, Friendly (+1% stats), Crush
+// (+3%), Love (+6%), Oath (+9%) or Oath200 (+12%). Oathing is in two tiers:
+// a ship is Oath right after the ring at 100 affinity and only reaches the
+// full +12% as Oath200, at 200 affinity.
+//
+// Enhanced records whether the ship's stats have been fully enhanced. It is
+// kept with the entry but doesn't change the stats: the tables from level 100
+// up already include full enhancement, and the wiki doesn't list the amounts
+// needed to take it off or add it below that.
+//
+// The gear list is the loadout by slot: three main slots then two auxiliary,
+// with null for an empty slot. Any other key is an error.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct RosterEntry {
+    pub name: String,
+    #[serde(default)]
+    pub retrofit: bool,
+    #[serde(default)]
+    pub level: Option<i32>,
+    #[serde(default)]
+    pub limit_break: Option<u8>,
+    #[serde(default)]
+    pub affinity: Option<Affinity>,
+    #[serde(default)]
+    pub enhanced: Option<bool>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub gear: Vec<Option<String>>,
+}
+
+impl RosterEntry {
+    pub fn parse(line: &str) -> Option<Self> {
+        let (name, level) = match line.rsplit_once('@') {
+            Some((name, level)) => (name, level.trim().parse::<i32>().ok()),
+            None => (line, None),
+        };
+        let (key, retrofit) = normalize_name(name);
+        if key.is_empty() {
+            return None;
+        }
+        Some(Self {
+            name: name.trim().to_string(),
+            retrofit,
+            level: level.map(|l| l.clamp(MIN_LEVEL, MAX_LEVEL)),
+            limit_break: None,
+            affinity: None,
+            enhanced: None,
+            gear: Vec::new(),
+        })
+    }
+
+    fn key(&self) -> (String, bool) {
+        let (name, retrofit) = normalize_name(&self.name);
+        (name, retrofit || self.retrofit)
+    }
+
+    // The level the ship's stats should be taken at. A level above what the
+    // limit break stage allows is capped, with a warning.
+    fn effective_level(&self, warnings: &mut Vec<String>) -> Option<i32> {
+        let level = self.level?.clamp(MIN_LEVEL, MAX_LEVEL);
+        let Some(limit_break) = self.limit_break else {
+            return Some(level);
+        };
+        let limit_break = limit_break.min(MAX_LIMIT_BREAK);
+        let cap = level_cap(limit_break);
+        if level > cap {
+            warnings.push(format!(
+                "{} is level {} but limit break {} caps it at {}",
+                self.name, level, limit_break, cap
+            ));
+            return Some(cap);
+        }
+        Some(level)
+    }
+
+    // Apply this entry's account progress and loadout to the base ship data
+    fn apply(&self, ship: &Ship, gear: &HashMap<String, Gear>, warnings: &mut Vec<String>) -> Ship {
+        let mut ship = match self.effective_level(warnings) {
+            Some(level) => ship_at_level(ship, level).unwrap_or_else(|| {
+                warnings.push(format!(
+                    "{} has no level tables, using its stats as loaded instead of level {}",
+                    ship.name, level
+                ));
+                ship.clone()
+            }),
+            None => ship.clone(),
+        };
+        if self.affinity.is_some() {
+            ship.affinity = self.affinity;
+        }
+        if !self.gear.is_empty() {
+            let (equipped, errors) = equip(&ship, &self.gear, gear);
+            warnings.extend(errors.iter().map(|e| format!("{}: {}", ship.name, e)));
+            ship.gear = equipped;
+        }
+        ship
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Roster {
+    pub entries: Vec<RosterEntry>,
+}
+
+// Result of matching a roster against the ship data
+#[derive(Debug, Clone, Default)]
+pub struct RosterMatch {
+    pub ships: HashMap<i32, Ship>,
+    pub unmatched: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+// Lowercase, collapse whitespace and strip a trailing "(Retrofit)" so that
+// "Suffolk (Retrofit)", "suffolk(retrofit)" and " Suffolk  " compare equal.
+// Returns the cleaned name and whether the retrofit suffix was present.
+pub fn normalize_name(name: &str) -> (String, bool) {
+    let lower = name.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    match lower.strip_suffix("(retrofit)") {
+        Some(base) => (base.trim_end().to_string(), true),
+        None => (lower, false),
+    }
+}
+
+impl Roster {
+    pub fn from_lines<'a, I: IntoIterator<Item = &'a str>>(lines: I) -> Self {
+        Self {
+            entries: lines.into_iter().filter_map(RosterEntry::parse).collect(),
+        }
+    }
+
+    // Keep only the ships listed in the roster. A retrofit entry falls back
+    // to the base ship when the data has no retrofit record for it. Loadouts
+    // are looked up in the gear data. When several ships share a name the
+    // one with the lowest id is used, so the same roster always picks the
+    // same ship.
+    pub fn filter(&self, map: &HashMap<i32, Ship>, gear: &HashMap<String, Gear>) -> RosterMatch {
+        let mut ids: Vec<&i32> = map.keys().collect();
+        ids.sort();
+        let mut by_name: HashMap<(String, bool), (i32, &Ship)> = HashMap::new();
+        for id in ids {
+            let ship = &map[id];
+            by_name.entry(normalize_name(&ship.name)).or_insert((*id, ship));
+        }
+
+        let mut result = RosterMatch::default();
+        for entry in &self.entries {
+            let (base, retrofit) = entry.key();
+            let found = by_name
+                .get(&(base.clone(), retrofit))
+                .or_else(|| if retrofit { by_name.get(&(base, false)) } else { None });
+
+            match found {
+                Some((id, ship)) => {
+                    let ship = entry.apply(ship, gear, &mut result.warnings);
+                    result.ships.insert(*id, ship);
+                }
+                None => result.unmatched.push(entry.name.clone()),
+            }
+        }
+        result
+    }
+}
+
+// Read a roster file. Files ending in .json are read as a list of entries,
+// anything else as one name per line.
+pub fn read_roster<P: AsRef<Path>>(path: P) -> Result<Roster, Box<dyn Error>> {
+    let is_json = path.as_ref().extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    let file = File::open(path)?;
+    let reader = BufReader::new(file);
+
+    if is_json {
+        let entries: Vec<RosterEntry> = serde_json::from_reader(reader)?;
+        return Ok(Roster { entries });
+    }
+
+    let lines = reader.lines().collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Roster::from_lines(lines.iter().map(String::as_str)))
+}
+
+// The roster the TUI and GUI import with: include.json when it exists,
+// otherwise include.txt
+pub fn default_roster_path() -> &'static str {
+    if Path::new("include.json").exists() {
+        "include.json"
+    } else {
+        "include.txt"
+    }
+}
+
+// Restrict the map to the owned ships in the roster file, warning about any
+// roster names that did not match a ship in the data.
+pub fn apply_roster<P: AsRef<Path>>(
+    path: P,
+    map: HashMap<i32, Ship>,
+    gear: &HashMap<String, Gear>,
+) -> Result<HashMap<i32, Ship>, Box<dyn Error>> {
+    let roster = read_roster(path)?;
+    let matched = roster.filter(&map, gear);
+
+    for name in &matched.unmatched {
+        eprintln!("Roster entry \"{}\" did not match any ship", name);
+    }
+    for warning in &matched.warnings {
+        eprintln!("{}", warning);
+    }
+
+    Ok(matched.ships)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scrape::tests::{find, fixture_ships, test_ship};
+    use crate::ship::Class;
+
+    fn entry(json: &str) -> RosterEntry {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn text_lines() {
+        let roster = Roster::from_lines(["Javelin @ 118", "  Suffolk (Retrofit) ", "", "Hood@200", "Cassin @ high"]);
+        let names: Vec<&str> = roster.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["Javelin", "Suffolk (Retrofit)", "Hood", "Cassin"]);
+
+        assert_eq!(roster.entries[0].level, Some(118));
+        assert!(roster.entries[1].retrofit);
+        assert_eq!(roster.entries[1].level, None);
+        // Levels are clamped and a level that isn't a number is ignored
+        assert_eq!(roster.entries[2].level, Some(MAX_LEVEL));
+        assert_eq!(roster.entries[3].level, None);
+    }
+
+    #[test]
+    fn json_entries() {
+        let javelin = entry(
+            r#"{"name": "Javelin", "level": 120, "limit_break": 3, "affinity": "Oath", "enhanced": true,
+                "gear": ["Quadruple 533mm Torpedo", null]}"#,
+        );
+        assert_eq!(javelin.level, Some(120));
+        assert_eq!(javelin.limit_break, Some(3));
+        assert_eq!(javelin.affinity, Some(Affinity::Oath));
+        assert_eq!(javelin.enhanced, Some(true));
+        assert_eq!(javelin.gear, vec![Some(String::from("Quadruple 533mm Torpedo")), None]);
+
+        let hood = entry(r#"{"name": "Hood"}"#);
+        assert_eq!(hood, Roster::from_lines(["Hood"]).entries[0]);
+    }
+
+    #[test]
+    fn json_rejects_unknown_keys() {
+        let error = serde_json::from_str::<RosterEntry>(r#"{"name": "Hood", "enhance": false}"#).unwrap_err();
+        assert!(error.to_string().contains("unknown field `enhance`"), "{}", error);
+        assert!(serde_json::from_str::<RosterEntry>(r#"{"level": 100}"#).is_err());
+    }
+
+    #[test]
+    fn level_capped_by_limit_break() {
+        for (limit_break, expected) in [(0, 70), (1, 80), (2, 90), (3, 120), (9, 120)] {
+            let entry = RosterEntry {
+                limit_break: Some(limit_break),
+                ..RosterEntry::parse("Hood @ 120").unwrap()
+            };
+            let mut warnings = Vec::new();
+            assert_eq!(entry.effective_level(&mut warnings), Some(expected));
+            assert_eq!(warnings.len(), usize::from(expected < 120), "{:?}", warnings);
+        }
+
+        // Without a limit break stage the level is taken as given
+        let mut warnings = Vec::new();
+        assert_eq!(RosterEntry::parse("Hood @ 120").unwrap().effective_level(&mut warnings), Some(120));
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn capped_level_sets_the_stats() {
+        let map = fixture_ships();
+        let roster = Roster {
+            entries: vec![entry(r#"{"name": "Hood", "level": 120, "limit_break": 2}"#)],
+        };
+        let matched = roster.filter(&map, &HashMap::new());
+        let hood = matched.ships.values().next().unwrap();
+        assert_eq!(hood.hp, ship_at_level(find(&map, "Hood"), 90).unwrap().hp);
+        assert_eq!(matched.warnings, ["Hood is level 120 but limit break 2 caps it at 90"]);
+    }
+
+    #[test]
+    fn names_match_loosely() {
+        assert_eq!(normalize_name("  Prinz   EUGEN "), (String::from("prinz eugen"), false));
+        assert_eq!(normalize_name("Suffolk (Retrofit)"), (String::from("suffolk"), true));
+        assert_eq!(normalize_name("suffolk(retrofit)"), (String::from("suffolk"), true));
+
+        let map = fixture_ships();
+        let roster = Roster::from_lines(["hood", "prinz  eugen", "JAVELIN(retrofit)", "Cassin (Retrofit)"]);
+        let matched = roster.filter(&map, &HashMap::new());
+        let mut names: Vec<&str> = matched.ships.values().map(|ship| ship.name.as_str()).collect();
+        names.sort();
+        // Cassin has no retrofit record, so the base ship is used
+        assert_eq!(names, ["Cassin", "Hood", "Javelin (Retrofit)", "Prinz Eugen"]);
+        assert!(matched.unmatched.is_empty());
+    }
+
+    #[test]
+    fn unmatched_names_are_reported() {
+        let map = fixture_ships();
+        // A plain name doesn't pick up a retrofit, so "Javelin" finds nothing
+        let roster = Roster::from_lines(["Hood", "Nagato", "Javelin", "Enterprise"]);
+        let matched = roster.filter(&map, &HashMap::new());
+        assert_eq!(matched.ships.len(), 2);
+        assert_eq!(matched.unmatched, ["Nagato", "Javelin"]);
+    }
+
+    #[test]
+    fn duplicate_names_pick_the_lowest_id() {
+        // Each new map hashes with its own seed, so iteration order varies
+        for order in [[7, 3, 5], [3, 5, 7], [5, 7, 3]] {
+            let map: HashMap<i32, Ship> = order.into_iter().map(|id| (id, test_ship("Hood", Class::BC))).collect();
+            let matched = Roster::from_lines(["Hood"]).filter(&map, &HashMap::new());
+            assert_eq!(matched.ships.keys().collect::<Vec<_>>(), [&3]);
+        }
+    }
+}
@@ -0,0 +1,319 @@
+use core::fmt;
+use std::collections::HashMap;
+use std::error::Error;
+use std::str::FromStr;
+
+use strum_macros::EnumString;
+
+use crate::ship::{Class, Ship, SortChoice};
+
+// Per-stat weights for composite scoring, e.g. "torpedo=1.0, reload=0.6, evasion=0.4"
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Weights {
+    pub weights: Vec<(SortChoice, f64)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeightsParseError(String);
+
+impl fmt::Display for WeightsParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid weights: {}", self.0)
+    }
+}
+
+impl Error for WeightsParseError {}
+
+impl FromStr for Weights {
+    type Err = WeightsParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut weights = Vec::new();
+
+        for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (stat, weight) = part
+                .split_once('=')
+                .ok_or_else(|| WeightsParseError(format!("expected stat=weight, got \"{}\"", part)))?;
+
+            let stat = SortChoice::from_str(stat.trim())
+                .map_err(|_| WeightsParseError(format!("unknown stat \"{}\"", stat.trim())))?;
+            let weight: f64 = weight
+                .trim()
+                .parse()
+                .map_err(|_| WeightsParseError(format!("bad weight \"{}\"", weight.trim())))?;
+
+            weights.push((stat, weight));
+        }
+
+        if weights.is_empty() {
+            return Err(WeightsParseError(String::from("no weights given")));
+        }
+
+        Ok(Self { weights })
+    }
+}
+
+// How raw stats are rescaled before weighting, so that e.g. HP (~5000)
+// does not drown out luck (~80) in a composite score
+#[derive(EnumString, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[strum(ascii_case_insensitive)]
+pub enum Normalization {
+    #[default]
+    None,
+    MinMax,
+    ZScore,
+    ClassPercentile,
+}
+
+impl Normalization {
+    pub const ALL: [Normalization; 4] = [
+        Normalization::None,
+        Normalization::MinMax,
+        Normalization::ZScore,
+        Normalization::ClassPercentile,
+    ];
+}
+
+impl fmt::Display for Normalization {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Normalization::None => write!(f, "None"),
+            Normalization::MinMax => write!(f, "MinMax"),
+            Normalization::ZScore => write!(f, "ZScore"),
+            Normalization::ClassPercentile => write!(f, "ClassPercentile"),
+        }
+    }
+}
+
+// Distribution of one stat over a set of ships
+#[derive(Debug, Clone, Default)]
+struct StatSummary {
+    sorted: Vec<f64>,
+    min: f64,
+    max: f64,
+    mean: f64,
+    std_dev: f64,
+}
+
+impl StatSummary {
+    fn new(mut values: Vec<f64>) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+        values.sort_by(f64::total_cmp);
+
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+
+        Self {
+            min: values[0],
+            max: values[values.len() - 1],
+            mean,
+            std_dev: variance.sqrt(),
+            sorted: values,
+        }
+    }
+
+    fn min_max(&self, value: f64) -> f64 {
+        if self.max > self.min {
+            (value - self.min) / (self.max - self.min)
+        } else {
+            0.0
+        }
+    }
+
+    fn z_score(&self, value: f64) -> f64 {
+        if self.std_dev > 0.0 {
+            (value - self.mean) / self.std_dev
+        } else {
+            0.0
+        }
+    }
+
+    // Fraction of ships below the value, counting ties as half
+    fn percentile(&self, value: f64) -> f64 {
+        if self.sorted.is_empty() {
+            return 0.0;
+        }
+        let below = self.sorted.partition_point(|v| *v < value);
+        let not_above = self.sorted.partition_point(|v| *v <= value);
+        (below as f64 + (not_above - below) as f64 / 2.0) / self.sorted.len() as f64
+    }
+}
+
+// Per-stat distributions computed over the loaded map or a class subset
+#[derive(Debug, Clone, Default)]
+pub struct Normalizer {
+    mode: Normalization,
+    overall: HashMap<SortChoice, StatSummary>,
+    per_class: HashMap<(Class, SortChoice), StatSummary>,
+}
+
+impl Normalizer {
+    pub fn new<'a, I: IntoIterator<Item = &'a Ship>>(ships: I, mode: Normalization) -> Self {
+        let ships: Vec<&Ship> = ships.into_iter().collect();
+        let mut normalizer = Self {
+            mode,
+            ..Self::default()
+        };
+        if mode == Normalization::None {
+            return normalizer;
+        }
+
+        for stat in SortChoice::all() {
+            let values = ships.iter().map(|s| stat.value(s) as f64).collect();
+            normalizer.overall.insert(stat, StatSummary::new(values));
+        }
+
+        if mode == Normalization::ClassPercentile {
+            let mut classes: HashMap<Class, Vec<&Ship>> = HashMap::new();
+            for ship in &ships {
+                classes.entry(ship.class.clone()).or_default().push(ship);
+            }
+            for (class, members) in classes {
+                for stat in SortChoice::all() {
+                    let values = members.iter().map(|s| stat.value(s) as f64).collect();
+                    normalizer.per_class.insert((class.clone(), stat), StatSummary::new(values));
+                }
+            }
+        }
+        normalizer
+    }
+
+    // Only consider ships of one class when computing the distributions
+    pub fn for_class<'a, I: IntoIterator<Item = &'a Ship>>(ships: I, class: &Class, mode: Normalization) -> Self {
+        Self::new(ships.into_iter().filter(|s| &s.class == class), mode)
+    }
+
+    pub fn mode(&self) -> Normalization {
+        self.mode
+    }
+
+    pub fn normalize(&self, ship: &Ship, stat: SortChoice) -> f64 {
+        let value = stat.value(ship) as f64;
+        match self.mode {
+            Normalization::None => value,
+            Normalization::MinMax => self.overall.get(&stat).map_or(0.0, |s| s.min_max(value)),
+            Normalization::ZScore => self.overall.get(&stat).map_or(0.0, |s| s.z_score(value)),
+            Normalization::ClassPercentile => self
+                .per_class
+                .get(&(ship.class.clone(), stat))
+                .map_or(0.0, |s| s.percentile(value)),
+        }
+    }
+}
+
+// Weights applied on top of normalized stats
+#[derive(Debug, Clone, Default)]
+pub struct Scorer {
+    pub weights: Weights,
+    pub normalizer: Normalizer,
+}
+
+impl Scorer {
+    pub fn new(weights: Weights, normalizer: Normalizer) -> Self {
+        Self { weights, normalizer }
+    }
+
+    pub fn score(&self, ship: &Ship) -> f64 {
+        self.weights
+            .weights
+            .iter()
+            .map(|(stat, weight)| weight * self.normalizer.normalize(ship, *stat))
+            .sum()
+    }
+}
+
+// Sort highest composite score first, ties by name
+pub fn sort_ships_weighted(line: &mut [Ship], scorer: &Scorer) {
+    line.sort_by(|a, b| {
+        scorer
+            .score(b)
+            .total_cmp(&scorer.score(a))
+            .then_with(|| a.name.cmp(&b.name))
+            .then_with(|| a.id.cmp(&b.id))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scrape::tests::test_ship;
+
+    fn ships(values: &[(&str, Class, i32)]) -> Vec<Ship> {
+        values
+            .iter()
+            .map(|(name, class, hp)| {
+                let mut ship = test_ship(name, class.clone());
+                ship.hp = *hp;
+                ship
+            })
+            .collect()
+    }
+
+    fn normalized(ships: &[Ship], mode: Normalization) -> Vec<f64> {
+        let normalizer = Normalizer::new(ships, mode);
+        ships.iter().map(|s| normalizer.normalize(s, SortChoice::HP)).collect()
+    }
+
+    fn close(actual: Vec<f64>, expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-9, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn parse_weights() {
+        let weights: Weights = " torpedo=1.0, Reload = 0.6,evasion=-0.4, ".parse().unwrap();
+        assert_eq!(
+            weights.weights,
+            vec![(SortChoice::Torpedo, 1.0), (SortChoice::Reload, 0.6), (SortChoice::Evasion, -0.4)]
+        );
+
+        let error = |input: &str| input.parse::<Weights>().unwrap_err().to_string();
+        assert_eq!(error("torpedo"), "invalid weights: expected stat=weight, got \"torpedo\"");
+        assert_eq!(error("torpedos=1"), "invalid weights: unknown stat \"torpedos\"");
+        assert_eq!(error("torpedo=lots"), "invalid weights: bad weight \"lots\"");
+        assert_eq!(error(" , "), "invalid weights: no weights given");
+    }
+
+    #[test]
+    fn weighted_sort_breaks_ties_by_name_then_id() {
+        let mut line = ships(&[("Beta", Class::DD, 100), ("Alpha", Class::DD, 100), ("Gamma", Class::DD, 300)]);
+        let mut twin = line[1].clone();
+        twin.id = String::from("Alpha 2");
+        line.insert(0, twin);
+
+        let scorer = Scorer::new("hp=1".parse().unwrap(), Normalizer::default());
+        sort_ships_weighted(&mut line, &scorer);
+        let order: Vec<(&str, &str)> = line.iter().map(|s| (s.name.as_str(), s.id.as_str())).collect();
+        assert_eq!(order, [("Gamma", "Gamma"), ("Alpha", "Alpha"), ("Alpha", "Alpha 2"), ("Beta", "Beta")]);
+    }
+
+    #[test]
+    fn normalization_modes() {
+        let line = ships(&[("A", Class::DD, 100), ("B", Class::DD, 200), ("C", Class::DD, 300), ("D", Class::BB, 5000)]);
+        close(normalized(&line, Normalization::None), &[100.0, 200.0, 300.0, 5000.0]);
+        close(normalized(&line[..3], Normalization::MinMax), &[0.0, 0.5, 1.0]);
+        // Population standard deviation of 100, 200, 300 is 81.65
+        let sd = (20000.0f64 / 3.0).sqrt();
+        close(normalized(&line[..3], Normalization::ZScore), &[-100.0 / sd, 0.0, 100.0 / sd]);
+        // Percentiles are within the class, so the battleship is alone in its class
+        close(normalized(&line, Normalization::ClassPercentile), &[1.0 / 6.0, 0.5, 5.0 / 6.0, 0.5]);
+
+        let destroyers = Normalizer::for_class(&line, &Class::DD, Normalization::MinMax);
+        assert_eq!(destroyers.normalize(&line[2], SortChoice::HP), 1.0);
+        assert_eq!(destroyers.mode(), Normalization::MinMax);
+    }
+
+    #[test]
+    fn normalization_when_every_ship_is_equal() {
+        let line = ships(&[("A", Class::DD, 250), ("B", Class::DD, 250), ("C", Class::DD, 250)]);
+        close(normalized(&line, Normalization::MinMax), &[0.0; 3]);
+        close(normalized(&line, Normalization::ZScore), &[0.0; 3]);
+        close(normalized(&line, Normalization::ClassPercentile), &[0.5; 3]);
+        close(normalized(&line, Normalization::None), &[250.0; 3]);
+    }
+}
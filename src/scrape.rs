@@ -0,0 +1,774 @@
+use core::fmt;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use scraper::{ElementRef, Html, Selector};
+
+use crate::gear::{Gear, GearType};
+use crate::ship::*;
+
+pub const WIKI_URL: &str = "https://azurlane.koumakan.jp/wiki/";
+pub const SHIP_STATS_PAGE: &str = "List_of_Ships_by_Stats";
+
+// Where wiki pages come from: the live site, or pages saved from it so that
+// scraping works without network access
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    Live,
+    Snapshot(PathBuf), // a saved page, or a directory of `<Page_Name>.html` files
+}
+
+impl Source {
+    // Treat an empty path as "use the live wiki"
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref();
+        if path.as_os_str().is_empty() {
+            Source::Live
+        } else {
+            Source::Snapshot(path.to_path_buf())
+        }
+    }
+
+    pub fn fetch(&self, page: &str) -> Result<String, Box<dyn Error>> {
+        match self {
+            Source::Live => Ok(reqwest::blocking::get(format!("{}{}", WIKI_URL, page))?.text()?),
+            Source::Snapshot(path) if path.is_dir() => {
+                let file = ["html", "htm"]
+                    .iter()
+                    .map(|ext| path.join(format!("{}.{}", page, ext)))
+                    .find(|file| file.is_file())
+                    .ok_or_else(|| format!("no saved copy of {} in {}", page, path.display()))?;
+                Ok(fs::read_to_string(file)?)
+            }
+            Source::Snapshot(path) => Ok(fs::read_to_string(path)?),
+        }
+    }
+}
+
+// Columns of the stats table we know how to read
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Column {
+    Id,
+    Name,
+    Rarity,
+    Nation,
+    Class,
+    Luck,
+    Armor,
+    Speed,
+    HP,
+    Firepower,
+    AntiAir,
+    Torpedo,
+    Evasion,
+    Aviation,
+    Cost,
+    Reload,
+    AntiSubmarine,
+    Oxygen,
+    Ammunition,
+    Accuracy,
+}
+
+// Header labels (lowercased, letters and digits only) for each column. The
+// stat headers on the wiki are icons, so these also cover their alt/title text.
+const HEADERS: [(&str, Column); 40] = [
+    ("id", Column::Id),
+    ("name", Column::Name),
+    ("shipname", Column::Name),
+    ("rarity", Column::Rarity),
+    ("nation", Column::Nation),
+    ("faction", Column::Nation),
+    ("type", Column::Class),
+    ("hull", Column::Class),
+    ("hulltype", Column::Class),
+    ("class", Column::Class),
+    ("luck", Column::Luck),
+    ("armor", Column::Armor),
+    ("speed", Column::Speed),
+    ("health", Column::HP),
+    ("hp", Column::HP),
+    ("firepower", Column::Firepower),
+    ("fp", Column::Firepower),
+    ("antiair", Column::AntiAir),
+    ("aa", Column::AntiAir),
+    ("torpedo", Column::Torpedo),
+    ("trp", Column::Torpedo),
+    ("torp", Column::Torpedo),
+    ("evasion", Column::Evasion),
+    ("eva", Column::Evasion),
+    ("aviation", Column::Aviation),
+    ("avi", Column::Aviation),
+    ("oilconsumption", Column::Cost),
+    ("cost", Column::Cost),
+    ("reload", Column::Reload),
+    ("rld", Column::Reload),
+    ("antisubmarinewarfare", Column::AntiSubmarine),
+    ("antisubmarine", Column::AntiSubmarine),
+    ("asw", Column::AntiSubmarine),
+    ("oxygen", Column::Oxygen),
+    ("ammunition", Column::Ammunition),
+    ("ammo", Column::Ammunition),
+    ("accuracy", Column::Accuracy),
+    ("accuracyhit", Column::Accuracy),
+    ("hit", Column::Accuracy),
+    ("acc", Column::Accuracy),
+];
+
+// Columns a row cannot be read without
+const REQUIRED: [Column; 4] = [Column::Id, Column::Name, Column::Class, Column::Armor];
+
+fn header_column(label: &str) -> Option<Column> {
+    let key: String = label
+        .trim_end_matches(".png")
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .collect::<String>()
+        .to_lowercase();
+    HEADERS.iter().find(|(name, _)| *name == key).map(|(_, column)| *column)
+}
+
+// A row of the wiki that could not be turned into a Ship
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowError {
+    pub table: String,
+    pub row: usize,
+    pub ship: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for RowError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.ship {
+            Some(ship) => write!(f, "{} row {} ({}): {}", self.table, self.row, ship, self.message),
+            None => write!(f, "{} row {}: {}", self.table, self.row, self.message),
+        }
+    }
+}
+
+// Everything that went wrong while scraping, collected instead of panicking
+#[derive(Debug, Clone, Default)]
+pub struct ScrapeReport {
+    pub rows_read: usize,
+    pub errors: Vec<RowError>,
+}
+
+impl ScrapeReport {
+    pub fn is_clean(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    fn error(&mut self, table: &str, row: usize, ship: Option<&str>, message: impl Into<String>) {
+        self.errors.push(RowError {
+            table: table.to_string(),
+            row,
+            ship: ship.map(String::from),
+            message: message.into(),
+        });
+    }
+}
+
+impl fmt::Display for ScrapeReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} rows read, {} skipped", self.rows_read, self.errors.len())?;
+        for error in &self.errors {
+            write!(f, "\n  {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+fn selector(css: &str) -> Selector {
+    Selector::parse(css).expect("static selector is valid")
+}
+
+// Text of a cell without footnote markers such as "[1]"
+fn cell_text(cell: ElementRef) -> String {
+    let mut text = String::new();
+    for node in cell.descendants() {
+        if let Some(t) = node.value().as_text() {
+            let in_footnote = node
+                .ancestors()
+                .filter_map(ElementRef::wrap)
+                .any(|e| matches!(e.value().name(), "sup" | "style" | "script"));
+            if !in_footnote {
+                text.push_str(t);
+            }
+        }
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// Header cells are often just an icon, so fall back to its alt or title text
+fn header_text(cell: ElementRef) -> String {
+    let text = cell_text(cell);
+    if !text.is_empty() {
+        return text;
+    }
+    let img = selector("img, a");
+    cell.select(&img)
+        .find_map(|e| e.value().attr("alt").or_else(|| e.value().attr("title")))
+        .or_else(|| cell.value().attr("title"))
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn parse_stat(value: Option<&String>) -> Result<i32, String> {
+    let value = match value {
+        Some(v) => v.replace(',', ""),
+        None => return Ok(0),
+    };
+    match value.trim() {
+        "" | "-" | "—" | "–" => Ok(0),
+        v => v.parse().map_err(|_| format!("\"{}\" is not a number", v)),
+    }
+}
+
+fn image_url(name: &str) -> String {
+    let mut image_url = String::from("https://azurlane.koumakan.jp/wiki/File:");
+    image_url.push_str(&name.replace(" (Retrofit)", "Kai").replace(' ', "_"));
+    image_url.push_str("Icon.png");
+    image_url
+}
+
+// The image a wiki `File:` page shows, as an absolute URL. `page_url` is
+// the address the page came from, used to resolve relative links.
+pub fn parse_file_page(html: &str, page_url: &str) -> Option<String> {
+    let document = Html::parse_document(html);
+    let link = selector(".fullImageLink a, .fullMedia a");
+    let og_image = selector("meta[property='og:image']");
+
+    let href = document
+        .select(&link)
+        .find_map(|a| a.value().attr("href"))
+        .or_else(|| document.select(&og_image).find_map(|m| m.value().attr("content")))?
+        .trim();
+
+    if href.starts_with("http://") || href.starts_with("https://") {
+        Some(href.to_string())
+    } else if let Some(rest) = href.strip_prefix("//") {
+        Some(format!("https://{}", rest))
+    } else if href.starts_with('/') {
+        // keep only the scheme and host of the page
+        let host_end = page_url.find("://").map(|i| i + 3)?;
+        let origin_end = page_url[host_end..].find('/').map_or(page_url.len(), |i| host_end + i);
+        Some(format!("{}{}", &page_url[..origin_end], href))
+    } else {
+        None
+    }
+}
+
+fn build_ship(cells: &HashMap<Column, String>) -> Result<Ship, String> {
+    let text = |column: Column| cells.get(&column).cloned().unwrap_or_default();
+    let stat = |column: Column| parse_stat(cells.get(&column)).map_err(|e| format!("{:?}: {}", column, e));
+
+    let name = text(Column::Name);
+    if name.is_empty() {
+        return Err(String::from("empty name"));
+    }
+    let class = Class::from_str(&text(Column::Class)).map_err(|_| format!("unknown hull type \"{}\"", text(Column::Class)))?;
+    let armor = Armor::from_str(&text(Column::Armor)).map_err(|_| format!("unknown armor \"{}\"", text(Column::Armor)))?;
+
+    Ok(Ship {
+        id: text(Column::Id),
+        image: image_url(&name),
+        name,
+        rarity: Rarity::from(text(Column::Rarity)),
+        nation: Nation::from(text(Column::Nation)),
+        class,
+        luck: stat(Column::Luck)?,
+        armor,
+        speed: stat(Column::Speed)?,
+        hp: stat(Column::HP)?,
+        firepower: stat(Column::Firepower)?,
+        antiair: stat(Column::AntiAir)?,
+        torpedo: stat(Column::Torpedo)?,
+        evasion: stat(Column::Evasion)?,
+        aviation: stat(Column::Aviation)?,
+        cost: stat(Column::Cost)?,
+        reload: stat(Column::Reload)?,
+        antisubmarine: stat(Column::AntiSubmarine)?,
+        oxygen: stat(Column::Oxygen)?,
+        ammunition: stat(Column::Ammunition)?,
+        accuracy: stat(Column::Accuracy)?,
+        levels: BTreeMap::new(),
+        affinity: None,
+        gear: Vec::new(),
+        skills: Vec::new(),
+        fleet_tech: None,
+    })
+}
+
+// Columns of a table's header row, which is the first row without data cells
+fn header_columns(table: ElementRef) -> Option<Vec<Option<Column>>> {
+    let header = table
+        .select(&selector("tr"))
+        .find(|row| row.select(&selector("td")).next().is_none())?;
+    Some(header.select(&selector("th")).map(|c| header_column(&header_text(c))).collect())
+}
+
+// Read a table row by row, mapping cells to columns by header text and
+// handing each row to `row_fn` along with its index. Rows that don't line up
+// with the header are reported and skipped.
+fn read_rows<F>(table: ElementRef, name: &str, required: &[Column], report: &mut ScrapeReport, mut row_fn: F)
+where
+    F: FnMut(usize, &HashMap<Column, String>, &mut ScrapeReport),
+{
+    let tr = selector("tr");
+    let cell = selector("th, td");
+    let td = selector("td");
+
+    let mut columns: Option<Vec<Option<Column>>> = None;
+
+    for (index, row) in table.select(&tr).enumerate() {
+        let cells: Vec<ElementRef> = row.select(&cell).collect();
+
+        // The first row without data cells is the header
+        if row.select(&td).next().is_none() {
+            if columns.is_none() {
+                let mapped: Vec<Option<Column>> = cells.iter().map(|c| header_column(&header_text(*c))).collect();
+                let missing: Vec<String> = required
+                    .iter()
+                    .filter(|r| !mapped.contains(&Some(**r)))
+                    .map(|r| format!("{:?}", r))
+                    .collect();
+                if !missing.is_empty() {
+                    report.error(name, index, None, format!("header is missing columns: {}", missing.join(", ")));
+                    return;
+                }
+                columns = Some(mapped);
+            }
+            continue;
+        }
+
+        let columns = match &columns {
+            Some(columns) => columns,
+            None => {
+                report.error(name, index, None, "data row before any header row");
+                return;
+            }
+        };
+        report.rows_read += 1;
+
+        if cells.len() != columns.len() {
+            let row_name = columns
+                .iter()
+                .position(|c| *c == Some(Column::Name))
+                .and_then(|i| cells.get(i))
+                .map(|c| cell_text(*c));
+            report.error(
+                name,
+                index,
+                row_name.as_deref(),
+                format!("expected {} cells, found {}", columns.len(), cells.len()),
+            );
+            continue;
+        }
+
+        let values: HashMap<Column, String> = columns
+            .iter()
+            .zip(cells.iter())
+            .filter_map(|(column, c)| column.map(|column| (column, cell_text(*c))))
+            .collect();
+        row_fn(index, &values, report);
+    }
+}
+
+// Read one stats table, turning each row into a Ship
+pub fn parse_ship_table(table: ElementRef, name: &str, report: &mut ScrapeReport) -> Vec<Ship> {
+    let mut ships = Vec::new();
+    read_rows(table, name, &REQUIRED, report, |index, values, report| match build_ship(values) {
+        Ok(ship) => ships.push(ship),
+        Err(message) => report.error(name, index, values.get(&Column::Name).map(String::as_str), message),
+    });
+    ships
+}
+
+// Pick the stats table for a level. Tabs are matched by their title when the
+// page has one ("Level 100"), otherwise by position: 1 > 100 > 120 > 125.
+fn level_table<'a>(document: &'a Html, level: i32) -> Option<ElementRef<'a>> {
+    let article = selector("article");
+    let table = selector("table");
+
+    let tabs: Vec<ElementRef> = document.select(&article).filter(|a| a.select(&table).next().is_some()).collect();
+    let title = format!("level {}", level);
+
+    let by_title = tabs.iter().find(|a| {
+        ["data-title", "title"]
+            .iter()
+            .filter_map(|attr| a.value().attr(attr))
+            .any(|t| t.to_lowercase().trim() == title)
+    });
+    let position = match level {
+        1 => 0,
+        100 => 1,
+        120 => 2,
+        125 => 3,
+        _ => 0,
+    };
+
+    by_title
+        .or_else(|| tabs.get(position))
+        .and_then(|a| a.select(&table).next())
+}
+
+pub fn parse_ships(html: &str, level: i32) -> Result<(HashMap<i32, Ship>, ScrapeReport), Box<dyn Error>> {
+    let document = Html::parse_document(html);
+    let table = level_table(&document, level).ok_or_else(|| format!("no stats table for level {}", level))?;
+
+    let mut report = ScrapeReport::default();
+    let mut ships = parse_ship_table(table, &format!("Level {}", level), &mut report);
+    if let Some(valid) = ValidLevel::from_number(level) {
+        for ship in &mut ships {
+            ship.levels.insert(valid, ship.stats());
+        }
+    }
+
+    let map = ships.into_iter().enumerate().map(|(i, ship)| (i as i32, ship)).collect();
+    Ok((map, report))
+}
+
+// Read every level table of the page and merge them per ship. The flat stat
+// fields hold the `current` level, or the highest level the ship has.
+pub fn parse_all_levels(html: &str, current: ValidLevel) -> Result<(HashMap<i32, Ship>, ScrapeReport), Box<dyn Error>> {
+    let document = Html::parse_document(html);
+    let mut report = ScrapeReport::default();
+    let mut merged: BTreeMap<String, Ship> = BTreeMap::new();
+
+    for level in ValidLevel::ALL {
+        let name = level.to_string();
+        let table = match level_table(&document, level.number()) {
+            Some(table) => table,
+            None => {
+                report.error(&name, 0, None, "table not found");
+                continue;
+            }
+        };
+
+        for ship in parse_ship_table(table, &name, &mut report) {
+            let stats = ship.stats();
+            merged.entry(ship.name.clone()).or_insert(ship).levels.insert(level, stats);
+        }
+    }
+
+    if merged.is_empty() {
+        return Err("no ship stats tables found".into());
+    }
+
+    let map = merged
+        .into_values()
+        .map(|mut ship| {
+            let stats = ship.levels.get(&current).or_else(|| ship.levels.values().next_back()).copied();
+            if let Some(stats) = stats {
+                ship.set_stats(&stats);
+            }
+            ship
+        })
+        .enumerate()
+        .map(|(i, ship)| (i as i32, ship))
+        .collect();
+    Ok((map, report))
+}
+
+pub fn scrape_wiki(source: &Source, level: i32) -> Result<(HashMap<i32, Ship>, ScrapeReport), Box<dyn Error>> {
+    let response = source.fetch(SHIP_STATS_PAGE)?;
+    parse_ships(&response, level)
+}
+
+pub fn scrape_wiki_all_levels(source: &Source, current: ValidLevel) -> Result<(HashMap<i32, Ship>, ScrapeReport), Box<dyn Error>> {
+    let response = source.fetch(SHIP_STATS_PAGE)?;
+    parse_all_levels(&response, current)
+}
+
+// Equipment list pages and the gear type of everything listed on them
+pub const EQUIPMENT_PAGES: [(&str, GearType); 15] = [
+    ("List_of_Destroyer_Guns", GearType::DDGun),
+    ("List_of_Light_Cruiser_Guns", GearType::CLGun),
+    ("List_of_Heavy_Cruiser_Guns", GearType::CAGun),
+    ("List_of_Large_Cruiser_Guns", GearType::CBGun),
+    ("List_of_Battleship_Guns", GearType::BBGun),
+    ("List_of_Torpedoes", GearType::Torpedo),
+    ("List_of_Submarine_Torpedoes", GearType::SubTorpedo),
+    ("List_of_AA_Guns", GearType::AAGun),
+    ("List_of_Fighters", GearType::Fighter),
+    ("List_of_Dive_Bombers", GearType::DiveBomber),
+    ("List_of_Torpedo_Bombers", GearType::TorpedoBomber),
+    ("List_of_Seaplanes", GearType::Seaplane),
+    ("List_of_Anti-Submarine_Equipment", GearType::ASW),
+    ("List_of_Auxiliary_Equipment", GearType::Auxiliary),
+    ("List_of_Cargo", GearType::Cargo),
+];
+
+// Every stat column of a row, named the same as the stat. Stats in `skip`
+// or without a column read as 0.
+fn read_stats(cells: &HashMap<Column, String>, skip: &[SortChoice]) -> Result<StatBlock, String> {
+    let mut stats = StatBlock::default();
+    for stat in SortChoice::all() {
+        let column = match header_column(stat.name()) {
+            Some(column) if !skip.contains(&stat) && stat != SortChoice::Armor => column,
+            _ => continue,
+        };
+        let value = parse_stat(cells.get(&column)).map_err(|e| format!("{:?}: {}", column, e))?;
+        stats.set(stat, value);
+    }
+    Ok(stats)
+}
+
+fn build_gear(cells: &HashMap<Column, String>, page_type: GearType) -> Result<Gear, String> {
+    let text = |column: Column| cells.get(&column).cloned().unwrap_or_default();
+
+    let name = text(Column::Name);
+    if name.is_empty() {
+        return Err(String::from("empty name"));
+    }
+
+    Ok(Gear {
+        name,
+        // A "Type" column narrows the page's type down, e.g. fighters on a
+        // mixed aircraft page
+        gear_type: GearType::from_str(&text(Column::Class)).unwrap_or(page_type),
+        rarity: Rarity::from(text(Column::Rarity)),
+        nation: Nation::from(text(Column::Nation)),
+        stats: read_stats(cells, &[SortChoice::Cost])?,
+    })
+}
+
+// Read every equipment table on a list page. Tables without a name column
+// (navigation boxes and the like) are skipped.
+pub fn parse_gear(html: &str, page: &str, page_type: GearType, report: &mut ScrapeReport) -> Vec<Gear> {
+    let document = Html::parse_document(html);
+    let mut gear = Vec::new();
+
+    for table in document.select(&selector("table")) {
+        let has_names = header_columns(table).is_some_and(|columns| columns.contains(&Some(Column::Name)));
+        if !has_names {
+            continue;
+        }
+        read_rows(table, page, &[Column::Name], report, |index, values, report| {
+            match build_gear(values, page_type) {
+                Ok(piece) => gear.push(piece),
+                Err(message) => report.error(page, index, values.get(&Column::Name).map(String::as_str), message),
+            }
+        });
+    }
+    gear
+}
+
+// Scrape every equipment list page. A page that can't be fetched is reported
+// and the rest are still read. Gear listed on more than one page is kept once.
+pub fn scrape_equipment(source: &Source) -> Result<(Vec<Gear>, ScrapeReport), Box<dyn Error>> {
+    if let Source::Snapshot(path) = source {
+        if !path.is_dir() {
+            return Err(format!("equipment needs a directory of saved pages, {} is a file", path.display()).into());
+        }
+    }
+
+    let mut report = ScrapeReport::default();
+    let mut merged: BTreeMap<String, Gear> = BTreeMap::new();
+    for (page, gear_type) in EQUIPMENT_PAGES {
+        let html = match source.fetch(page) {
+            Ok(html) => html,
+            Err(e) => {
+                report.error(page, 0, None, e.to_string());
+                continue;
+            }
+        };
+        for piece in parse_gear(&html, page, gear_type, &mut report) {
+            merged.entry(piece.name.clone()).or_insert(piece);
+        }
+    }
+
+    if merged.is_empty() {
+        return Err(format!("no equipment found\n{}", report).into());
+    }
+    Ok((merged.into_values().collect(), report))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::sync::OnceLock;
+
+    fn fixtures() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures")
+    }
+
+    fn scrape_fixture(level: i32) -> (HashMap<i32, Ship>, ScrapeReport) {
+        scrape_wiki(&Source::Snapshot(fixtures()), level).unwrap()
+    }
+
+    // The six fixture ships at level 100, with every level table, for the
+    // other modules' tests. Parsed once and copied for each caller.
+    pub(crate) fn fixture_ships() -> HashMap<i32, Ship> {
+        static SHIPS: OnceLock<HashMap<i32, Ship>> = OnceLock::new();
+        SHIPS
+            .get_or_init(|| scrape_wiki_all_levels(&Source::Snapshot(fixtures()), ValidLevel::Level100).unwrap().0)
+            .clone()
+    }
+
+    pub(crate) fn find<'a>(map: &'a HashMap<i32, Ship>, name: &str) -> &'a Ship {
+        map.values().find(|s| s.name == name).unwrap()
+    }
+
+    // A made-up ship with Hood's stats, for tests that set the stats they need
+    pub(crate) fn test_ship(name: &str, class: Class) -> Ship {
+        let mut ship = find(&fixture_ships(), "Hood").clone();
+        ship.id = name.to_string();
+        ship.name = name.to_string();
+        ship.class = class;
+        ship.levels.clear();
+        ship
+    }
+
+    #[test]
+    fn file_page_image() {
+        let html = fs::read_to_string(fixtures().join("File_JavelinKaiIcon.png.html")).unwrap();
+        let page = "https://azurlane.koumakan.jp/wiki/File:JavelinKaiIcon.png";
+        assert_eq!(
+            parse_file_page(&html, page).as_deref(),
+            Some("https://azurlane.koumakan.jp/w/images/3/3c/JavelinKaiIcon.png")
+        );
+        assert_eq!(parse_file_page("<html><body></body></html>", page), None);
+    }
+
+    #[test]
+    fn level_1_table() {
+        let (map, report) = scrape_fixture(1);
+        assert!(report.is_clean(), "{}", report);
+        assert_eq!(map.len(), 6);
+
+        let cassin = find(&map, "Cassin");
+        assert_eq!(cassin.id, "5");
+        assert_eq!(cassin.class, Class::DD);
+        assert_eq!(cassin.armor, Armor::Light);
+        assert_eq!(cassin.hp, 282);
+        assert_eq!(cassin.accuracy, 70);
+        assert_eq!(find(&map, "Enterprise").aviation, 73);
+    }
+
+    #[test]
+    fn level_100_table() {
+        let (map, report) = scrape_fixture(100);
+        assert!(report.is_clean(), "{}", report);
+
+        assert_eq!(find(&map, "Cassin").hp, 1446);
+        assert_eq!(find(&map, "Enterprise").aviation, 394);
+        // the footnote marker is not part of the name
+        let hood = find(&map, "Hood");
+        assert_eq!(hood.class, Class::BC);
+        assert_eq!(hood.firepower, 325);
+    }
+
+    #[test]
+    fn level_120_table() {
+        let (map, report) = scrape_fixture(120);
+        assert!(report.is_clean(), "{}", report);
+
+        assert_eq!(find(&map, "Enterprise").aviation, 418);
+        let javelin = find(&map, "Javelin (Retrofit)");
+        assert_eq!(javelin.torpedo, 292);
+        assert_eq!(javelin.image, "https://azurlane.koumakan.jp/wiki/File:JavelinKaiIcon.png");
+    }
+
+    #[test]
+    fn level_125_table() {
+        let (map, report) = scrape_fixture(125);
+        assert!(report.is_clean(), "{}", report);
+
+        let i19 = find(&map, "I-19");
+        assert_eq!(i19.class, Class::SS);
+        assert_eq!(i19.hp, 1163);
+        assert_eq!(i19.speed, 0);
+        assert_eq!(i19.oxygen, 150);
+        assert_eq!(i19.ammunition, 12);
+    }
+
+    #[test]
+    fn file_and_directory_snapshots_agree() {
+        let file = fixtures().join(format!("{}.html", SHIP_STATS_PAGE));
+        let (from_file, _) = scrape_wiki(&Source::Snapshot(file), 100).unwrap();
+        let (from_dir, _) = scrape_fixture(100);
+
+        let mut from_file: Vec<Ship> = from_file.into_values().collect();
+        let mut from_dir: Vec<Ship> = from_dir.into_values().collect();
+        from_file.sort();
+        from_dir.sort();
+        assert_eq!(from_file, from_dir);
+    }
+
+    #[test]
+    fn broken_rows_are_reported() {
+        let (map, report) = scrape_wiki(&Source::Snapshot(fixtures().join("broken")), 1).unwrap();
+
+        let mut names: Vec<&str> = map.values().map(|s| s.name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, ["Cassin", "I-19"]);
+
+        assert_eq!(report.rows_read, 5);
+        assert_eq!(report.errors.len(), 3);
+        assert!(report.errors[0].message.contains("CVX"));
+        assert_eq!(report.errors[1].ship.as_deref(), Some("Placeholder"));
+        assert_eq!(report.errors[2].ship.as_deref(), Some("Hood"));
+    }
+
+    #[test]
+    fn missing_snapshot_page() {
+        let source = Source::Snapshot(fixtures().join("broken"));
+        assert!(source.fetch("List_of_Equipment").is_err());
+    }
+
+    #[test]
+    fn all_levels_in_one_pass() {
+        let (map, report) = scrape_wiki_all_levels(&Source::Snapshot(fixtures()), ValidLevel::Level120).unwrap();
+        assert!(report.is_clean(), "{}", report);
+        assert_eq!(map.len(), 6);
+
+        let enterprise = find(&map, "Enterprise");
+        assert_eq!(enterprise.aviation, 418);
+        assert_eq!(enterprise.levels.len(), 4);
+        assert_eq!(enterprise.at_level(ValidLevel::Level1).unwrap().aviation, 73);
+        assert_eq!(enterprise.at_level(ValidLevel::Level125).unwrap().aviation, 429);
+    }
+
+    #[test]
+    fn equipment_pages() {
+        let (gear, report) = scrape_equipment(&Source::Snapshot(fixtures())).unwrap();
+
+        let names: Vec<&str> = gear.iter().map(|g| g.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "Bow Torpedo (6 rounds)",
+                "Quadruple 533mm Torpedo",
+                "Quintuple 533mm Magnetic Torpedo",
+                "Single 127mm (5\"/38 Mk 12)",
+                "Twin 100mm (Type 98)",
+                "Twin 120mm (4.7\"/45 Mk XII)",
+            ]
+        );
+        let gun = &gear[4];
+        assert_eq!(gun.gear_type, GearType::DDGun);
+        assert_eq!(gun.nation, Nation::SakuraEmpire);
+        assert_eq!(gun.stats.get(SortChoice::Firepower), 10);
+        assert_eq!(gun.stats.get(SortChoice::AntiAir), 45);
+        assert_eq!(gear[5].stats.get(SortChoice::Firepower), 5);
+        // the type column wins over the page's type when it has one
+        assert_eq!(gear[0].gear_type, GearType::SubTorpedo);
+        assert_eq!(gear[2].gear_type, GearType::Torpedo);
+
+        // the broken row and every page without a saved copy are reported
+        assert_eq!(report.rows_read, 7);
+        assert_eq!(report.errors.len(), EQUIPMENT_PAGES.len() - 2 + 1);
+        let broken = report.errors.iter().find(|e| e.table == "List_of_Destroyer_Guns").unwrap();
+        assert_eq!(broken.ship.as_deref(), Some("Broken Gun"));
+    }
+
+    #[test]
+    fn equipment_needs_a_directory() {
+        let source = Source::Snapshot(fixtures().join("List_of_Torpedoes.html"));
+        assert!(scrape_equipment(&source).is_err());
+    }
+}
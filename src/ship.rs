@@ -1,10 +1,19 @@
 use strum_macros::EnumString;
 use serde::{Deserialize, Serialize};
 use core::fmt;
-use iced::widget::image;
+use std::cmp::Ordering;
+use std::mem;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::str::FromStr;
+use std::ops::Add;
 
+use crate::gear::{gear_bonus, Gear};
+use crate::skill::Skill;
 
-#[derive(EnumString, Debug, Clone, Eq, PartialEq, Copy)]
+
+#[derive(EnumString, Debug, Clone, Eq, PartialEq, Copy, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+#[strum(ascii_case_insensitive)]
 pub enum SortChoice {
     Luck,
     Armor,
@@ -14,6 +23,7 @@ pub enum SortChoice {
     AntiAir,
     Torpedo,
     Evasion,
+    Aviation,
     Cost,
     Reload,
     AntiSubmarine,
@@ -22,45 +32,201 @@ pub enum SortChoice {
     Accuracy,
 }
 
+// Everything the program needs to know about one sortable stat. Sorting,
+// menus, radios and display all go through STATS, so adding a stat to
+// Ship only needs a new SortChoice variant and a row here.
+pub struct StatDescriptor {
+    pub choice: SortChoice,
+    pub name: &'static str,
+    pub get: fn(&Ship) -> i32,
+    pub set: fn(&mut Ship, i32), // armor has no number to set and ignores it
+    pub direction: SortDirection, // which way is "better" for this stat
+}
+
+pub const STAT_COUNT: usize = 15;
+
+pub static STATS: [StatDescriptor; STAT_COUNT] = [
+    StatDescriptor { choice: SortChoice::HP, name: "HP", get: |s| s.hp, set: |s, v| s.hp = v, direction: SortDirection::Descending },
+    StatDescriptor { choice: SortChoice::Luck, name: "Luck", get: |s| s.luck, set: |s, v| s.luck = v, direction: SortDirection::Descending },
+    StatDescriptor { choice: SortChoice::Armor, name: "Armor", get: |s| s.armor.value(), set: |_, _| {}, direction: SortDirection::Descending },
+    StatDescriptor { choice: SortChoice::Speed, name: "Speed", get: |s| s.speed, set: |s, v| s.speed = v, direction: SortDirection::Descending },
+    StatDescriptor { choice: SortChoice::Firepower, name: "Firepower", get: |s| s.firepower, set: |s, v| s.firepower = v, direction: SortDirection::Descending },
+    StatDescriptor { choice: SortChoice::AntiAir, name: "AntiAir", get: |s| s.antiair, set: |s, v| s.antiair = v, direction: SortDirection::Descending },
+    StatDescriptor { choice: SortChoice::Torpedo, name: "Torpedo", get: |s| s.torpedo, set: |s, v| s.torpedo = v, direction: SortDirection::Descending },
+    StatDescriptor { choice: SortChoice::Evasion, name: "Evasion", get: |s| s.evasion, set: |s, v| s.evasion = v, direction: SortDirection::Descending },
+    StatDescriptor { choice: SortChoice::Aviation, name: "Aviation", get: |s| s.aviation, set: |s, v| s.aviation = v, direction: SortDirection::Descending },
+    StatDescriptor { choice: SortChoice::Cost, name: "Cost", get: |s| s.cost, set: |s, v| s.cost = v, direction: SortDirection::Ascending },
+    StatDescriptor { choice: SortChoice::Reload, name: "Reload", get: |s| s.reload, set: |s, v| s.reload = v, direction: SortDirection::Descending },
+    StatDescriptor { choice: SortChoice::AntiSubmarine, name: "AntiSubmarine", get: |s| s.antisubmarine, set: |s, v| s.antisubmarine = v, direction: SortDirection::Descending },
+    StatDescriptor { choice: SortChoice::Oxygen, name: "Oxygen", get: |s| s.oxygen, set: |s, v| s.oxygen = v, direction: SortDirection::Descending },
+    StatDescriptor { choice: SortChoice::Ammunition, name: "Ammunition", get: |s| s.ammunition, set: |s, v| s.ammunition = v, direction: SortDirection::Descending },
+    StatDescriptor { choice: SortChoice::Accuracy, name: "Accuracy", get: |s| s.accuracy, set: |s, v| s.accuracy = v, direction: SortDirection::Descending },
+];
+
 impl SortChoice {
-    pub fn all() -> [SortChoice; 14] {
-        [
-            SortChoice::HP,
-            SortChoice::Luck,
-            SortChoice::Armor,
-            SortChoice::Speed,
-            SortChoice::Firepower,
-            SortChoice::AntiAir,
-            SortChoice::Torpedo,
-            SortChoice::Evasion,
-            SortChoice::Cost,
-            SortChoice::Reload,
-            SortChoice::AntiSubmarine,
-            SortChoice::Oxygen,
-            SortChoice::Ammunition,
-            SortChoice::Accuracy,
-        ]
+    pub fn all() -> [SortChoice; STAT_COUNT] {
+        core::array::from_fn(|i| STATS[i].choice)
+    }
+
+    // Position of the stat in STATS, which is also its slot in a StatBlock
+    pub fn index(&self) -> usize {
+        STATS
+            .iter()
+            .position(|d| d.choice == *self)
+            .expect("every SortChoice has a row in STATS")
+    }
+
+    pub fn descriptor(&self) -> &'static StatDescriptor {
+        STATS
+            .iter()
+            .find(|d| d.choice == *self)
+            .expect("every SortChoice has a row in STATS")
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.descriptor().name
+    }
+
+    // Numeric value of this stat for a ship, including its affinity bonus,
+    // fleet tech and equipment
+    pub fn value(&self, ship: &Ship) -> i32 {
+        let value = self.base_value(ship);
+        let hull = match ship.affinity {
+            Some(affinity) if Affinity::boosts(*self) => affinity.boost(value),
+            _ => value,
+        };
+        let tech = ship.fleet_tech.map_or(0, |t| t.get(*self));
+        hull + tech + ship.gear.iter().map(|g| g.stats.get(*self)).sum::<i32>()
+    }
+
+    // Numeric value of this stat as listed in the ship data
+    pub fn base_value(&self, ship: &Ship) -> i32 {
+        (self.descriptor().get)(ship)
+    }
+
+    pub fn default_direction(&self) -> SortDirection {
+        self.descriptor().direction
+    }
+
+    // 1-based place of a ship among `ships` when sorted best first by this
+    // stat. Ties share the better place.
+    pub fn rank(&self, ship: &Ship, ships: &[Ship]) -> usize {
+        let key = SortKey::from(*self);
+        1 + ships.iter().filter(|other| key.compare(other, ship) == Ordering::Less).count()
+    }
+}
+
+#[derive(EnumString, Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[strum(ascii_case_insensitive)]
+pub enum SortDirection {
+    #[strum(serialize = "Ascending", serialize = "asc")]
+    Ascending,
+    #[strum(serialize = "Descending", serialize = "desc")]
+    Descending,
+}
+
+impl SortDirection {
+    pub const ALL: [SortDirection; 2] = [SortDirection::Descending, SortDirection::Ascending];
+
+    pub fn apply(&self, ordering: Ordering) -> Ordering {
+        match self {
+            SortDirection::Ascending => ordering,
+            SortDirection::Descending => ordering.reverse(),
+        }
     }
 }
 
+impl fmt::Display for SortDirection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SortDirection::Ascending => write!(f, "Ascending"),
+            SortDirection::Descending => write!(f, "Descending"),
+        }
+    }
+}
+
+// One step of a sort: a stat and the direction to order it in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SortKey {
+    pub stat: SortChoice,
+    pub direction: SortDirection,
+}
+
+impl SortKey {
+    pub fn new(stat: SortChoice, direction: SortDirection) -> Self {
+        Self { stat, direction }
+    }
+
+    pub fn compare(&self, a: &Ship, b: &Ship) -> Ordering {
+        self.direction.apply(self.stat.value(a).cmp(&self.stat.value(b)))
+    }
+
+    // Parse a chain such as "firepower:desc, cost:asc, luck". A key without a
+    // direction uses the stat's default (descending, except ascending cost).
+    pub fn parse_chain(input: &str) -> Result<Vec<SortKey>, SortKeyParseError> {
+        input
+            .split(',')
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .map(SortKey::from_str)
+            .collect()
+    }
+}
+
+impl From<SortChoice> for SortKey {
+    fn from(stat: SortChoice) -> Self {
+        Self::new(stat, stat.default_direction())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SortKeyParseError(String);
+
+impl fmt::Display for SortKeyParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid sort key \"{}\"", self.0)
+    }
+}
+
+impl Error for SortKeyParseError {}
+
+impl FromStr for SortKey {
+    type Err = SortKeyParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || SortKeyParseError(s.to_string());
+        let (stat, direction) = match s.split_once(':') {
+            Some((stat, direction)) => (stat.trim(), Some(direction.trim())),
+            None => (s.trim(), None),
+        };
+
+        let stat = SortChoice::from_str(stat).map_err(|_| error())?;
+        let direction = match direction {
+            Some(direction) => SortDirection::from_str(direction).map_err(|_| error())?,
+            None => stat.default_direction(),
+        };
+        Ok(Self::new(stat, direction))
+    }
+}
+
+// Compare by each key in turn, then by name and id so equal stats always
+// come out in the same order
+pub fn compare_ships(a: &Ship, b: &Ship, keys: &[SortKey]) -> Ordering {
+    keys.iter()
+        .fold(Ordering::Equal, |ordering, key| ordering.then_with(|| key.compare(a, b)))
+        .then_with(|| a.name.cmp(&b.name))
+        .then_with(|| a.id.cmp(&b.id))
+}
+
 impl From<SortChoice> for String {
     fn from(sort_choice: SortChoice) -> String {
-        String::from(match sort_choice {
-            SortChoice::HP => "HP",
-            SortChoice::Luck => "Luck",
-            SortChoice::Armor => "Armor",
-            SortChoice::Speed => "Speed",
-            SortChoice::Firepower => "Firepower",
-            SortChoice::AntiAir => "AntiAir",
-            SortChoice::Torpedo => "Torpedo",
-            SortChoice::Evasion => "Evasion",
-            SortChoice::Cost => "Cost",
-            SortChoice::Reload => "Reload",
-            SortChoice::AntiSubmarine => "AntiSubmarine",
-            SortChoice::Oxygen => "Oxygen",
-            SortChoice::Ammunition => "Ammunition",
-            SortChoice::Accuracy => "Accuracy",
-        })
+        String::from(sort_choice.name())
+    }
+}
+
+impl fmt::Display for SortChoice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
     }
 }
 
@@ -72,7 +238,176 @@ pub enum Armor {
     Light,
 }
 
-#[derive(Debug, PartialEq, EnumString, Deserialize, Serialize, Clone, Eq, PartialOrd, Ord)]
+impl Armor {
+    pub const ALL: [Armor; 3] = [Armor::Light, Armor::Medium, Armor::Heavy];
+
+    pub fn value(&self) -> i32 {
+        match self {
+            Armor::Heavy => 3,
+            Armor::Medium => 2,
+            Armor::Light => 1,
+        }
+    }
+}
+
+impl fmt::Display for Armor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+// Letters and digits only, lowercased, so "Super Rare", "super rare" and
+// "SuperRare" all compare equal
+fn loose_key(text: &str) -> String {
+    text.chars().filter(|c| c.is_alphanumeric()).collect::<String>().to_lowercase()
+}
+
+// Rarity and Nation are both the wiki's names for a fixed list of values,
+// plus Unknown(text) for anything added since so it still groups together.
+// This writes the shared parts: the enum itself, ALL in declaration order,
+// Display with the wiki's names, parsing that ignores case and spacing (with
+// extra aliases), serde as plain text, and comparisons that put known values
+// in declaration order before Unknown ones sorted by their loose text.
+macro_rules! wiki_enum {
+    (
+        pub enum $name:ident {
+            $($variant:ident => $text:literal,)*
+        }
+        aliases { $($alias:literal => $target:ident,)* }
+    ) => {
+        #[derive(Debug, Deserialize, Serialize, Clone)]
+        #[serde(from = "String", into = "String")]
+        pub enum $name {
+            $($variant,)*
+            Unknown(String),
+        }
+
+        impl $name {
+            pub const ALL: [$name; [$($text),*].len()] = [$($name::$variant),*];
+
+            fn key(&self) -> (usize, String) {
+                match self {
+                    $name::Unknown(text) => ($name::ALL.len(), loose_key(text)),
+                    known => {
+                        let rank = $name::ALL.iter().position(|k| mem::discriminant(k) == mem::discriminant(known));
+                        (rank.unwrap_or_default(), String::new())
+                    }
+                }
+            }
+        }
+
+        impl PartialEq for $name {
+            fn eq(&self, other: &Self) -> bool {
+                self.key() == other.key()
+            }
+        }
+
+        impl Eq for $name {}
+
+        impl std::hash::Hash for $name {
+            fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+                self.key().hash(state)
+            }
+        }
+
+        impl PartialOrd for $name {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Ord for $name {
+            fn cmp(&self, other: &Self) -> Ordering {
+                self.key().cmp(&other.key())
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                match self {
+                    $($name::$variant => write!(f, $text),)*
+                    $name::Unknown(text) => write!(f, "{}", text),
+                }
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(text: &str) -> Self {
+                let key = loose_key(text);
+                $(
+                    if key == loose_key($alias) {
+                        return $name::$target;
+                    }
+                )*
+                $name::ALL
+                    .iter()
+                    .find(|known| loose_key(&known.to_string()) == key)
+                    .cloned()
+                    .unwrap_or_else(|| $name::Unknown(text.trim().to_string()))
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(text: String) -> Self {
+                $name::from(text.as_str())
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(value: $name) -> Self {
+                value.to_string()
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = std::convert::Infallible;
+
+            fn from_str(text: &str) -> Result<Self, Self::Err> {
+                Ok($name::from(text))
+            }
+        }
+    };
+}
+
+// Stored as the wiki's text ("Super Rare"). The wiki calls Normal "Common" in
+// places.
+wiki_enum! {
+    pub enum Rarity {
+        Normal => "Normal",
+        Rare => "Rare",
+        Elite => "Elite",
+        SuperRare => "Super Rare",
+        UltraRare => "Ultra Rare",
+        Priority => "Priority",
+        Decisive => "Decisive",
+    }
+    aliases {
+        "Common" => Normal,
+    }
+}
+
+// Stored as the wiki's faction name ("Eagle Union"). Collaborations and
+// factions added later are kept as Unknown with their original text.
+wiki_enum! {
+    pub enum Nation {
+        EagleUnion => "Eagle Union",
+        RoyalNavy => "Royal Navy",
+        SakuraEmpire => "Sakura Empire",
+        IronBlood => "Iron Blood",
+        DragonEmpery => "Dragon Empery",
+        SardegnaEmpire => "Sardegna Empire",
+        NorthernParliament => "Northern Parliament",
+        IrisLibre => "Iris Libre",
+        VichyaDominion => "Vichya Dominion",
+        Tempesta => "Tempesta",
+        KingdomOfTulipa => "Kingdom of Tulipa",
+        Meta => "META",
+        Universal => "Universal",
+    }
+    aliases {}
+}
+
+#[derive(Debug, PartialEq, EnumString, Deserialize, Serialize, Clone, Eq, PartialOrd, Ord, Hash)]
 pub enum Class {
     AE,
     AM,
@@ -134,9 +469,46 @@ impl Class {
         Class::AR,
         Class::AE,
     ];
+
+    // The class a picker or filter lists this one under. The IX variants all
+    // show as "IX", so picking IX has to take in every one of them.
+    pub fn family(&self) -> Class {
+        match self {
+            Class::IXs | Class::IXv | Class::IXm => Class::IX,
+            other => other.clone(),
+        }
+    }
+}
+
+// The three lines a fleet is made of, and which one a ship goes in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Line {
+    Vanguard,
+    Main,
+    Subs,
+}
+
+impl Line {
+    pub const ALL: [Line; 3] = [Line::Vanguard, Line::Main, Line::Subs];
+
+    pub fn of(ship: &Ship) -> Line {
+        if Class::FRONT.contains(&ship.class) {
+            Line::Vanguard
+        } else if Class::BACK.contains(&ship.class) {
+            Line::Main
+        } else {
+            Line::Subs
+        }
+    }
 }
 
-#[derive(Debug, PartialEq, EnumString, Deserialize, Serialize, Clone)]
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, PartialEq, EnumString, Deserialize, Serialize, Clone, Copy, Eq, Hash, PartialOrd, Ord)]
 pub enum ValidLevel {
     Level1,
     Level100,
@@ -144,14 +516,176 @@ pub enum ValidLevel {
     Level125,
 }
 
+impl ValidLevel {
+    pub const ALL: [ValidLevel; 4] = [
+        ValidLevel::Level1,
+        ValidLevel::Level100,
+        ValidLevel::Level120,
+        ValidLevel::Level125,
+    ];
+
+    pub fn number(&self) -> i32 {
+        match self {
+            ValidLevel::Level1 => 1,
+            ValidLevel::Level100 => 100,
+            ValidLevel::Level120 => 120,
+            ValidLevel::Level125 => 125,
+        }
+    }
+
+    pub fn from_number(level: i32) -> Option<ValidLevel> {
+        ValidLevel::ALL.iter().copied().find(|l| l.number() == level)
+    }
+}
+
+impl fmt::Display for ValidLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Level {}", self.number())
+    }
+}
+
+// How attached a ship is to the commander, from the roster
+#[derive(Debug, PartialEq, EnumString, Deserialize, Serialize, Clone, Copy, Eq, Hash, PartialOrd, Ord, Default)]
+#[strum(ascii_case_insensitive)]
+pub enum Affinity {
+    Disappointed,
+    #[default]
+    Stranger,
+    Friendly,
+    Crush,
+    Love,
+    Oath,
+    Oath200,
+}
+
+impl Affinity {
+    pub const ALL: [Affinity; 7] = [
+        Affinity::Disappointed,
+        Affinity::Stranger,
+        Affinity::Friendly,
+        Affinity::Crush,
+        Affinity::Love,
+        Affinity::Oath,
+        Affinity::Oath200,
+    ];
+
+    // Stats the affinity bonus applies to. Luck, speed, cost, oxygen,
+    // ammunition and armor are never boosted.
+    pub const BOOSTED: [SortChoice; 9] = [
+        SortChoice::HP,
+        SortChoice::Firepower,
+        SortChoice::AntiAir,
+        SortChoice::Torpedo,
+        SortChoice::Evasion,
+        SortChoice::Aviation,
+        SortChoice::Reload,
+        SortChoice::AntiSubmarine,
+        SortChoice::Accuracy,
+    ];
+
+    // Percentage added to the boosted stats. An oathed ship gets +9% until
+    // its affinity reaches 200, then +12%.
+    pub fn bonus_percent(&self) -> i32 {
+        match self {
+            Affinity::Disappointed | Affinity::Stranger => 0,
+            Affinity::Friendly => 1,
+            Affinity::Crush => 3,
+            Affinity::Love => 6,
+            Affinity::Oath => 9,
+            Affinity::Oath200 => 12,
+        }
+    }
+
+    pub fn boosts(stat: SortChoice) -> bool {
+        Affinity::BOOSTED.contains(&stat)
+    }
+
+    // A boosted stat with the bonus added, rounded down like the game does
+    pub fn boost(&self, value: i32) -> i32 {
+        value + value * self.bonus_percent() / 100
+    }
+}
+
+// The stats a ship fights with at the given affinity
+pub fn effective_stats(ship: &Ship, affinity: Affinity) -> StatBlock {
+    let stats = ship.stats();
+    StatBlock::from_fn(|stat| match stats.get(stat) {
+        value if Affinity::boosts(stat) => affinity.boost(value),
+        value => value,
+    })
+}
+
+impl fmt::Display for Affinity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Affinity::Oath200 => write!(f, "Oath (200)"),
+            _ => write!(f, "{:?}", self),
+        }
+    }
+}
+
+// The stats of a ship that change with its level, also used for the
+// bonuses equipment adds. One slot per row of STATS; armor is not part of a
+// stat block and always reads as 0. Files store it as an object keyed by the
+// lowercased stat name, e.g. {"hp": 45, "antiair": 10}, with missing stats 0.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, Eq, PartialEq, PartialOrd, Ord)]
+#[serde(from = "BTreeMap<String, i32>", into = "BTreeMap<String, i32>")]
+pub struct StatBlock([i32; STAT_COUNT]);
 
+impl StatBlock {
+    pub fn from_fn(mut value: impl FnMut(SortChoice) -> i32) -> Self {
+        let mut block = StatBlock::default();
+        for stat in SortChoice::all() {
+            block.set(stat, value(stat));
+        }
+        block
+    }
+
+    pub fn get(&self, stat: SortChoice) -> i32 {
+        self.0[stat.index()]
+    }
+
+    pub fn set(&mut self, stat: SortChoice, value: i32) {
+        if stat != SortChoice::Armor {
+            self.0[stat.index()] = value;
+        }
+    }
+
+    fn key(stat: SortChoice) -> String {
+        stat.name().to_lowercase()
+    }
+}
+
+impl From<BTreeMap<String, i32>> for StatBlock {
+    fn from(values: BTreeMap<String, i32>) -> Self {
+        StatBlock::from_fn(|stat| values.get(&StatBlock::key(stat)).copied().unwrap_or(0))
+    }
+}
+
+impl From<StatBlock> for BTreeMap<String, i32> {
+    fn from(block: StatBlock) -> Self {
+        SortChoice::all()
+            .into_iter()
+            .filter(|&stat| stat != SortChoice::Armor)
+            .map(|stat| (StatBlock::key(stat), block.get(stat)))
+            .collect()
+    }
+}
+
+impl Add for StatBlock {
+    type Output = StatBlock;
+
+    fn add(self, other: StatBlock) -> StatBlock {
+        StatBlock::from_fn(|stat| self.get(stat) + other.get(stat))
+    }
+}
 
 #[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq, PartialOrd, Ord)]
 pub struct Ship {
     pub id: String,
     pub name: String,
-    pub rarity: String,
-    pub nation: String,
+    pub rarity: Rarity,
+    pub nation: Nation,
     pub class: Class,
     pub luck: i32,
     pub armor: Armor,
@@ -169,6 +703,20 @@ pub struct Ship {
     pub ammunition: i32,
     pub accuracy: i32,
     pub image: String,
+    // Stats at every level scraped, when more than one table was read
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub levels: BTreeMap<ValidLevel, StatBlock>,
+    // Affinity from the roster, when the roster records one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub affinity: Option<Affinity>,
+    // Equipment from the roster loadout
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub gear: Vec<Gear>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skills: Vec<Skill>,
+    // Fleet tech bonus for the ship's class
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fleet_tech: Option<StatBlock>,
 }
 
 impl fmt::Display for Ship {
@@ -179,14 +727,149 @@ impl fmt::Display for Ship {
 
 
 impl Ship {
-    pub fn retrieve_img(&self) -> image::Handle {
+    // The stats currently in the flat fields
+    pub fn stats(&self) -> StatBlock {
+        StatBlock::from_fn(|stat| stat.base_value(self))
+    }
 
-        // TODO: actually use the image field and download the ship images
-        println!("{}", self.image);
+    // The stats with the roster's affinity bonus, fleet tech and equipment applied
+    pub fn effective_stats(&self) -> StatBlock {
+        effective_stats(self, self.affinity.unwrap_or_default())
+            + self.fleet_tech.unwrap_or_default()
+            + gear_bonus(&self.gear)
+    }
 
-        let image_test: image::Handle = image::Handle::from_path("test.png");
+    pub fn set_stats(&mut self, stats: &StatBlock) {
+        for stat in SortChoice::all() {
+            (stat.descriptor().set)(self, stats.get(stat));
+        }
+    }
 
-        image_test
+    // A copy of the ship with its stats at the given level, if that level was scraped
+    pub fn at_level(&self, level: ValidLevel) -> Option<Ship> {
+        let stats = self.levels.get(&level)?;
+        let mut ship = self.clone();
+        ship.set_stats(stats);
+        Some(ship)
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter_line_class;
+    use crate::scrape::tests::{find, fixture_ships, test_ship};
+
+    fn cassin() -> Ship {
+        find(&fixture_ships(), "Cassin").clone()
+    }
+
+    #[test]
+    fn affinity_boosts_only_combat_stats() {
+        let ship = cassin();
+        let base = ship.stats();
+        let oath = effective_stats(&ship, Affinity::Oath200);
+        for stat in SortChoice::all() {
+            if Affinity::boosts(stat) && base.get(stat) > 0 {
+                assert!(oath.get(stat) > base.get(stat), "{} not boosted", stat);
+            } else {
+                assert_eq!(oath.get(stat), base.get(stat), "{} boosted", stat);
+            }
+        }
+        for stat in [SortChoice::Luck, SortChoice::Speed, SortChoice::Cost, SortChoice::Oxygen, SortChoice::Ammunition] {
+            assert!(!Affinity::boosts(stat));
+        }
+        assert_eq!(effective_stats(&ship, Affinity::Stranger), base);
+    }
+
+    #[test]
+    fn affinity_bonus_rounds_down() {
+        let ship = cassin();
+        assert_eq!(ship.hp, 1446);
+        assert_eq!(ship.firepower, 66);
+        // 1446 * 12% = 173.52
+        assert_eq!(effective_stats(&ship, Affinity::Oath200).get(SortChoice::HP), 1619);
+        // 1446 * 9% = 130.14, the first oath tier
+        assert_eq!(effective_stats(&ship, Affinity::Oath).get(SortChoice::HP), 1576);
+        // 66 * 6% = 3.96
+        assert_eq!(effective_stats(&ship, Affinity::Love).get(SortChoice::Firepower), 69);
+        // 66 * 1% = 0.66
+        assert_eq!(effective_stats(&ship, Affinity::Friendly).get(SortChoice::Firepower), 66);
+    }
+
+    #[test]
+    fn roster_affinity_feeds_sorting() {
+        let mut ship = cassin();
+        ship.affinity = Some(Affinity::Oath200);
+        assert_eq!(SortChoice::HP.value(&ship), 1619);
+        assert_eq!(SortChoice::HP.base_value(&ship), 1446);
+        assert_eq!(SortChoice::Luck.value(&ship), ship.luck);
+    }
+
+    #[test]
+    fn wiki_names_parse_loosely() {
+        assert_eq!(Rarity::from("super rare"), Rarity::SuperRare);
+        assert_eq!(Rarity::from("SuperRare"), Rarity::SuperRare);
+        assert_eq!(Rarity::from("Common"), Rarity::Normal);
+        assert_eq!(Nation::from("meta"), Nation::Meta);
+        assert_eq!(Nation::Meta.to_string(), "META");
+
+        // Unknown values keep their text but compare loosely
+        assert_eq!(Nation::from(" Hololive "), Nation::Unknown(String::from("Hololive")));
+        assert_eq!(Nation::from("hololive"), Nation::from("Hololive"));
+        assert!(Nation::from("Hololive") > Nation::Universal);
+        assert!(Rarity::Normal < Rarity::Decisive);
+        assert_eq!(Rarity::ALL.len(), 7);
+        assert_eq!(Nation::ALL.len(), 13);
+
+        let json = serde_json::to_string(&Rarity::UltraRare).unwrap();
+        assert_eq!(json, "\"Ultra Rare\"");
+        assert_eq!(serde_json::from_str::<Rarity>(&json).unwrap(), Rarity::UltraRare);
+    }
+
+    #[test]
+    fn ix_filter_takes_the_whole_family() {
+        let line: Vec<Ship> = [Class::IXs, Class::IXv, Class::SS, Class::IX]
+            .into_iter()
+            .map(|class| test_ship(&format!("{:?}", class), class))
+            .collect();
+        let names = |ships: Vec<&Ship>| ships.into_iter().map(|s| s.name.clone()).collect::<Vec<_>>();
+
+        assert_eq!(names(filter_line_class(&line, Class::IX)), ["IXs", "IXv", "IX"]);
+        assert_eq!(names(filter_line_class(&line, Class::IXs)), ["IXs", "IXv", "IX"]);
+        assert_eq!(names(filter_line_class(&line, Class::SS)), ["SS"]);
+    }
+
+    fn ranks(stat: SortChoice) -> Vec<(String, usize)> {
+        let ships: Vec<Ship> = fixture_ships().into_values().collect();
+        let mut ranks: Vec<(String, usize)> = ships.iter().map(|s| (s.name.clone(), stat.rank(s, &ships))).collect();
+        ranks.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+        ranks
+    }
+
+    fn places(places: &[(&str, usize)]) -> Vec<(String, usize)> {
+        places.iter().map(|(name, place)| (name.to_string(), *place)).collect()
+    }
+
+    #[test]
+    fn rank_higher_is_better() {
+        assert_eq!(
+            ranks(SortChoice::HP),
+            places(&[("Hood", 1), ("Enterprise", 2), ("Prinz Eugen", 3), ("Javelin (Retrofit)", 4), ("Cassin", 5), ("I-19", 6)])
+        );
+    }
+
+    #[test]
+    fn rank_lower_is_better_with_ties() {
+        // I-19 costs 5, Cassin and Javelin 7 each, then 10, 13 and 15
+        assert_eq!(
+            ranks(SortChoice::Cost),
+            places(&[("I-19", 1), ("Cassin", 2), ("Javelin (Retrofit)", 2), ("Prinz Eugen", 4), ("Enterprise", 5), ("Hood", 6)])
+        );
+        // Everyone but the carrier has no aviation, and they all share second
+        let aviation = ranks(SortChoice::Aviation);
+        assert_eq!(aviation[0], (String::from("Enterprise"), 1));
+        assert!(aviation[1..].iter().all(|(_, place)| *place == 2));
+    }
+}
@@ -0,0 +1,285 @@
+use core::fmt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use strum_macros::EnumString;
+
+use crate::roster::normalize_name;
+use crate::ship::{Ship, SortChoice};
+
+pub const DEFAULT_SKILLS_FILE: &str = "skills.json";
+
+// When a skill goes off
+#[derive(EnumString, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+#[strum(ascii_case_insensitive)]
+pub enum Trigger {
+    Passive,
+    BattleStart,
+    Periodic,
+    OnFire,
+    OnAirstrike,
+    OnHit,
+    LowHp,
+}
+
+impl Trigger {
+    // Best guess from the skill text. Anything without a recognisable
+    // condition is treated as passive.
+    pub fn from_description(description: &str) -> Trigger {
+        let text = description.to_lowercase();
+        let has = |phrases: &[&str]| phrases.iter().any(|p| text.contains(p));
+
+        if has(&["start of the battle", "start of battle", "battle starts", "start of each battle"]) {
+            Trigger::BattleStart
+        } else if has(&["hp falls below", "hp drops below", "hp is below", "hp is under"]) {
+            Trigger::LowHp
+        } else if has(&["airstrike", "air strike"]) {
+            Trigger::OnAirstrike
+        } else if text.contains("every") && text.contains("second") {
+            Trigger::Periodic
+        } else if has(&["when firing", "when this ship fires", "main gun", "barrage"]) {
+            Trigger::OnFire
+        } else if has(&["when hit", "when taking damage", "when this ship takes damage"]) {
+            Trigger::OnHit
+        } else {
+            Trigger::Passive
+        }
+    }
+}
+
+impl fmt::Display for Trigger {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Trigger::BattleStart => write!(f, "Battle start"),
+            Trigger::OnFire => write!(f, "On firing"),
+            Trigger::OnAirstrike => write!(f, "On airstrike"),
+            Trigger::OnHit => write!(f, "On hit"),
+            Trigger::LowHp => write!(f, "Low HP"),
+            _ => write!(f, "{:?}", self),
+        }
+    }
+}
+
+// Words that mark a sentence as changing stats
+const STAT_VERBS: [&str; 6] = ["increase", "decrease", "raise", "reduce", "boost", "lower"];
+
+// Names the wiki uses for each stat in skill text. A phrase comes before any
+// shorter name inside it, so "reloading speed" is read as reload, not speed.
+const STAT_WORDS: [(&str, SortChoice); 18] = [
+    ("reloading speed", SortChoice::Reload),
+    ("reload speed", SortChoice::Reload),
+    ("firepower", SortChoice::Firepower),
+    ("anti-air", SortChoice::AntiAir),
+    ("anti air", SortChoice::AntiAir),
+    ("torpedo", SortChoice::Torpedo),
+    ("evasion", SortChoice::Evasion),
+    ("aviation", SortChoice::Aviation),
+    ("reload", SortChoice::Reload),
+    ("anti-submarine", SortChoice::AntiSubmarine),
+    ("asw", SortChoice::AntiSubmarine),
+    ("accuracy", SortChoice::Accuracy),
+    ("luck", SortChoice::Luck),
+    ("speed", SortChoice::Speed),
+    ("max hp", SortChoice::HP),
+    ("health", SortChoice::HP),
+    ("oxygen", SortChoice::Oxygen),
+    ("oil", SortChoice::Cost),
+];
+
+// Stats a skill raises or lowers, read from the sentences of its text that
+// mention a stat change. Returns them in the order of SortChoice::all().
+pub fn affected_stats(description: &str) -> Vec<SortChoice> {
+    let text = description.to_lowercase();
+    let mut found: Vec<SortChoice> = Vec::new();
+
+    for sentence in text.split(['.', ';']) {
+        if !STAT_VERBS.iter().any(|v| sentence.contains(v)) {
+            continue;
+        }
+        let mut sentence = sentence.to_string();
+        for (word, stat) in STAT_WORDS {
+            let starts = word_starts(&sentence, word);
+            if starts.is_empty() {
+                continue;
+            }
+            if !found.contains(&stat) {
+                found.push(stat);
+            }
+            // Blank out the match so a shorter name inside it isn't read too
+            for start in starts {
+                sentence.replace_range(start..start + word.len(), &" ".repeat(word.len()));
+            }
+        }
+    }
+    SortChoice::all().into_iter().filter(|s| found.contains(s)).collect()
+}
+
+// Where `word` starts in `text` each time it appears on word boundaries, so
+// "oil" doesn't match "foil"
+fn word_starts(text: &str, word: &str) -> Vec<usize> {
+    text.match_indices(word)
+        .map(|(start, _)| start)
+        .filter(|&start| {
+            let before = text[..start].chars().next_back();
+            let after = text[start + word.len()..].chars().next();
+            !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+        })
+        .collect()
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Skill {
+    pub name: String,
+    pub description: String,
+    // Filled in from the description when the file leaves them out
+    #[serde(default)]
+    pub trigger: Option<Trigger>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub affects: Vec<SortChoice>,
+}
+
+impl Skill {
+    pub fn trigger(&self) -> Trigger {
+        self.trigger.unwrap_or_else(|| Trigger::from_description(&self.description))
+    }
+
+    fn fill_in(&mut self) {
+        self.trigger = Some(self.trigger());
+        if self.affects.is_empty() {
+            self.affects = affected_stats(&self.description);
+        }
+    }
+}
+
+impl fmt::Display for Skill {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} [{}]", self.name, self.trigger())?;
+        if !self.affects.is_empty() {
+            let stats: Vec<&str> = self.affects.iter().map(|s| s.name()).collect();
+            write!(f, " {}", stats.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+fn skill_key(name: &str) -> String {
+    match normalize_name(name) {
+        (base, true) => format!("{} (retrofit)", base),
+        (base, false) => base,
+    }
+}
+
+// Skills keyed by ship name, from a file like
+// {"Javelin": [{"name": "Beam Me Up!", "description": "..."}]}
+pub fn read_skills_from_file<P: AsRef<Path>>(path: P) -> Result<HashMap<String, Vec<Skill>>, Box<dyn Error>> {
+    let file = File::open(path)?;
+    let reader = BufReader::new(file);
+
+    let skills: HashMap<String, Vec<Skill>> = serde_json::from_reader(reader)?;
+
+    Ok(skills
+        .into_iter()
+        .map(|(name, mut skills)| {
+            skills.iter_mut().for_each(Skill::fill_in);
+            (skill_key(&name), skills)
+        })
+        .collect())
+}
+
+// Give every ship the skills listed under its name. A retrofit uses the base
+// ship's skills unless it has its own entry.
+pub fn attach_skills(map: &mut HashMap<i32, Ship>, skills: &HashMap<String, Vec<Skill>>) {
+    for ship in map.values_mut() {
+        let base = normalize_name(&ship.name).0;
+        if let Some(found) = skills.get(&skill_key(&ship.name)).or_else(|| skills.get(&base)) {
+            ship.skills = found.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Javelin's "Beam Me Up!"
+    const BEAM_ME_UP: &str = "Every 20 seconds: 30% (60%) chance to increase this ship's Evasion by 30% (60%) for 8s.";
+    // Hood's "Glory of the Royal Navy"
+    const GLORY: &str = "At the start of the battle, increases the Firepower and Reload of all Royal Navy ships in your fleet by 5% (15%).";
+    // Enterprise's "Lucky E"
+    const LUCKY_E: &str =
+        "When launching an airstrike: 40% (70%) chance to deal 2x damage and have this ship take 50% less damage for 8s.";
+    // A shared repair skill
+    const EMERGENCY_REPAIR: &str = "When this ship's HP falls below 30% for the first time, restores 15% (25%) of its max HP.";
+    // Shared by many destroyers; neither a trigger nor a stat
+    const ANTI_DD: &str = "Reduces damage taken from enemy Destroyers by 10% (30%).";
+
+    #[test]
+    fn triggers() {
+        assert_eq!(Trigger::from_description(BEAM_ME_UP), Trigger::Periodic);
+        assert_eq!(Trigger::from_description(GLORY), Trigger::BattleStart);
+        assert_eq!(Trigger::from_description(LUCKY_E), Trigger::OnAirstrike);
+        assert_eq!(Trigger::from_description(EMERGENCY_REPAIR), Trigger::LowHp);
+        assert_eq!(
+            Trigger::from_description("Every 15 times the main gun is fired, fires a special barrage."),
+            Trigger::OnFire
+        );
+        assert_eq!(Trigger::from_description(ANTI_DD), Trigger::Passive);
+    }
+
+    #[test]
+    fn affected_stats_in_stat_order() {
+        assert_eq!(affected_stats(BEAM_ME_UP), [SortChoice::Evasion]);
+        assert_eq!(affected_stats(GLORY), [SortChoice::Firepower, SortChoice::Reload]);
+        assert_eq!(
+            affected_stats("Decreases oil cost by 1. Increases this ship's Anti-Air by 20%."),
+            [SortChoice::AntiAir, SortChoice::Cost]
+        );
+    }
+
+    #[test]
+    fn stats_need_a_stat_change() {
+        // Mentions firepower, but nothing raises or lowers it
+        assert!(affected_stats("Deals damage based on this ship's Firepower.").is_empty());
+        assert!(affected_stats(LUCKY_E).is_empty());
+        assert!(affected_stats(EMERGENCY_REPAIR).is_empty());
+        assert!(affected_stats(ANTI_DD).is_empty());
+    }
+
+    #[test]
+    fn whole_words_only() {
+        assert_eq!(word_starts("increases reload by 5%", "reload"), [10]);
+        assert!(word_starts("increases reloading speed", "reload").is_empty());
+        assert!(word_starts("tinfoil", "oil").is_empty());
+        assert_eq!(word_starts("(oil)", "oil"), [1]);
+        assert_eq!(word_starts("luck", "luck"), [0]);
+        // A later match counts when an earlier one is inside a word
+        assert_eq!(word_starts("reloading and reload", "reload"), [14]);
+
+        // The phrase is reload, not speed
+        assert_eq!(affected_stats("Increases reloading speed of the main gun by 10%."), [SortChoice::Reload]);
+        assert_eq!(affected_stats("Increases reload speed by 5% and Speed by 3."), [SortChoice::Speed, SortChoice::Reload]);
+    }
+
+    #[test]
+    fn skills_fill_in_from_the_text() {
+        let mut skill = Skill {
+            name: String::from("Glory of the Royal Navy"),
+            description: GLORY.to_string(),
+            trigger: None,
+            affects: Vec::new(),
+        };
+        skill.fill_in();
+        assert_eq!(skill.trigger, Some(Trigger::BattleStart));
+        assert_eq!(skill.to_string(), "Glory of the Royal Navy [Battle start] Firepower, Reload");
+
+        // Values given in the file win over the guess
+        let skill = Skill {
+            trigger: Some(Trigger::Passive),
+            ..skill
+        };
+        assert_eq!(skill.trigger(), Trigger::Passive);
+    }
+}
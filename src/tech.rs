@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use crate::ship::{Class, Ship, StatBlock};
+
+pub const DEFAULT_TECH_FILE: &str = "fleet_tech.json";
+
+// Account-wide fleet technology stat bonuses for each hull class, from a file like
+// {"DD": {"hp": 45, "torpedo": 15}, "CV": {"aviation": 22}}
+pub type FleetTech = HashMap<Class, StatBlock>;
+
+pub fn read_tech_from_file<P: AsRef<Path>>(path: P) -> Result<FleetTech, Box<dyn Error>> {
+    let file = File::open(path)?;
+    let reader = BufReader::new(file);
+
+    Ok(serde_json::from_reader(reader)?)
+}
+
+// Record each ship's class bonus on the ship so effective stats include it
+pub fn apply_fleet_tech(map: &mut HashMap<i32, Ship>, tech: &FleetTech) {
+    for ship in map.values_mut() {
+        ship.fleet_tech = tech.get(&ship.class).copied();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scrape::tests::fixture_ships;
+    use crate::ship::SortChoice;
+
+    #[test]
+    fn reads_and_applies_class_bonuses() {
+        let path = std::env::temp_dir().join(format!("fleet_tech_{}.json", std::process::id()));
+        std::fs::write(&path, r#"{"DD": {"hp": 45, "torpedo": 15}, "CV": {"aviation": 22}}"#).unwrap();
+        let tech = read_tech_from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(tech.len(), 2);
+        assert_eq!(tech[&Class::DD].get(SortChoice::Torpedo), 15);
+        assert_eq!(tech[&Class::DD].get(SortChoice::Firepower), 0);
+
+        let mut map = fixture_ships();
+        apply_fleet_tech(&mut map, &tech);
+        for ship in map.values() {
+            match ship.class {
+                Class::DD => {
+                    assert_eq!(SortChoice::HP.value(ship), ship.hp + 45);
+                    assert_eq!(SortChoice::Torpedo.value(ship), ship.torpedo + 15);
+                    assert_eq!(SortChoice::Aviation.value(ship), ship.aviation);
+                }
+                Class::CV => assert_eq!(SortChoice::Aviation.value(ship), ship.aviation + 22),
+                _ => assert_eq!(ship.fleet_tech, None, "{}", ship.name),
+            }
+        }
+    }
+
+    #[test]
+    fn unknown_class_is_an_error() {
+        let path = std::env::temp_dir().join(format!("fleet_tech_bad_{}.json", std::process::id()));
+        std::fs::write(&path, r#"{"Battleship": {"hp": 10}}"#).unwrap();
+        let result = read_tech_from_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(result.is_err());
+        assert!(read_tech_from_file("no_such_fleet_tech.json").is_err());
+    }
+}